@@ -0,0 +1,75 @@
+// Terrain lighting: the PBR lighting StandardMaterial gives, with the albedo and normal map read
+// through the tile UVs, plus a horizon shadow mask read at the fragment's world position. The
+// mask only blocks the sun, so ambient light still reaches shadowed ground.
+#import bevy_pbr::mesh_vertex_output    MeshVertexOutput
+#import bevy_pbr::mesh_view_bindings    view
+#import bevy_pbr::pbr_types             STANDARD_MATERIAL_FLAGS_DOUBLE_SIDED_BIT
+#import bevy_pbr::pbr_functions as pbr_functions
+#import bevy_pbr::ambient as ambient
+#import bevy_core_pipeline::tonemapping screen_space_dither, powsafe, tone_mapping
+
+// World XZ of the map's minimum corner in xy, and the map's world size in zw.
+@group(1) @binding(0) var<uniform> map_rect: vec4<f32>;
+@group(1) @binding(1) var albedo_texture: texture_2d<f32>;
+@group(1) @binding(2) var albedo_sampler: sampler;
+@group(1) @binding(3) var normal_map_texture: texture_2d<f32>;
+@group(1) @binding(4) var normal_map_sampler: sampler;
+@group(1) @binding(5) var shadow_mask_texture: texture_2d<f32>;
+@group(1) @binding(6) var shadow_mask_sampler: sampler;
+
+const PERCEPTUAL_ROUGHNESS: f32 = 0.9;
+
+@fragment
+fn fragment(
+    in: MeshVertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> @location(0) vec4<f32> {
+    let albedo = textureSampleBias(albedo_texture, albedo_sampler, in.uv, view.mip_bias);
+    let Nt = textureSampleBias(normal_map_texture, normal_map_sampler, in.uv, view.mip_bias).rgb;
+    let mask_uv = (in.world_position.xz - map_rect.xy) / map_rect.zw;
+    let sunlit = textureSample(shadow_mask_texture, shadow_mask_sampler, mask_uv).r;
+
+    var pbr_input = pbr_functions::pbr_input_new();
+    pbr_input.material.base_color = albedo;
+    pbr_input.material.perceptual_roughness = PERCEPTUAL_ROUGHNESS;
+    pbr_input.material.flags |= STANDARD_MATERIAL_FLAGS_DOUBLE_SIDED_BIT;
+    pbr_input.frag_coord = in.position;
+    pbr_input.world_position = in.world_position;
+    pbr_input.world_normal = pbr_functions::prepare_world_normal(in.world_normal, true, is_front);
+    pbr_input.is_orthographic = view.projection[3].w == 1.0;
+    pbr_input.V = pbr_functions::calculate_view(in.world_position, pbr_input.is_orthographic);
+
+    // The same tangent-space mapping StandardMaterial applies to its normal map.
+    let T = in.world_tangent.xyz;
+    let B = in.world_tangent.w * cross(in.world_normal, T);
+    let n = Nt * 2.0 - 1.0;
+    pbr_input.N = normalize(n.x * T + n.y * B + n.z * in.world_normal);
+
+    let lit = pbr_functions::pbr(pbr_input);
+
+    // Keep the ambient share and scale the direct light by the mask.
+    let NdotV = max(dot(pbr_input.N, pbr_input.V), 0.0001);
+    let reflectance = pbr_input.material.reflectance;
+    let F0 = vec3(0.16 * reflectance * reflectance);
+    let indirect = ambient::ambient_light(
+        in.world_position,
+        pbr_input.N,
+        pbr_input.V,
+        NdotV,
+        albedo.rgb,
+        F0,
+        PERCEPTUAL_ROUGHNESS,
+        pbr_input.occlusion,
+    );
+    var color = vec4(indirect + (lit.rgb - indirect) * sunlit, lit.a);
+
+#ifdef TONEMAP_IN_SHADER
+    color = tone_mapping(color, view.color_grading);
+#ifdef DEBAND_DITHER
+    var rgb = powsafe(color.rgb, 1.0 / 2.2);
+    rgb = rgb + screen_space_dither(in.position.xy);
+    color = vec4(powsafe(rgb, 2.2), color.a);
+#endif
+#endif
+    return color;
+}
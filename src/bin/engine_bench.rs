@@ -0,0 +1,142 @@
+use dustfall::engine::{add_human, ContainerId, Engine, Fluid, Gas, Solid, Volume};
+use std::time::{Duration, Instant};
+
+// Timing the fastest of a few identical runs keeps scheduler noise out of the comparison.
+const RUNS: usize = 3;
+// CH2O stocked in each crewed room; a crew member eats 3 per tick.
+const CREW_FOOD: i64 = 100_000;
+
+fn usage() -> ! {
+    eprintln!("usage: engine_bench [--implicit] [grid_size [ticks]]");
+    std::process::exit(2);
+}
+
+// Times ticks on a grid of piped rooms; use a release build for meaningful numbers.
+fn main() {
+    let (flags, args): (Vec<String>, Vec<String>) = std::env::args()
+        .skip(1)
+        .partition(|arg| arg.starts_with("--"));
+    let implicit = match flags.as_slice() {
+        [] => false,
+        [flag] if flag == "--implicit" => true,
+        _ => usage(),
+    };
+    let parse = |index: usize, default: usize| match args.get(index) {
+        Some(value) => value.parse().unwrap_or_else(|_| usage()),
+        None => default,
+    };
+    let grid_size = parse(0, 64);
+    let ticks = parse(1, 100);
+    if grid_size == 0 || ticks == 0 || args.len() > 2 {
+        usage();
+    }
+
+    let template = grid_engine(grid_size);
+    let mut best = Duration::MAX;
+    let mut engine = template.clone();
+    for _ in 0..RUNS {
+        engine = template.clone();
+        let start = Instant::now();
+        for _ in 0..ticks {
+            if implicit {
+                engine.tick_implicit();
+            } else {
+                engine.tick();
+            }
+        }
+        best = best.min(start.elapsed());
+    }
+
+    // A crew without food never breathes, which would leave only the pipes being timed.
+    let food = |engine: &Engine| -> i64 {
+        engine
+            .container_ids()
+            .map(|id| engine.container(id).solid().ch2o)
+            .sum()
+    };
+    assert!(
+        food(&engine) < food(&template),
+        "no reaction ran during the benchmark"
+    );
+
+    let ticks_per_second = ticks as f64 / best.as_secs_f64();
+    // The checksum only changes if the simulation does, so runs are comparable.
+    let checksum: i64 = engine
+        .container_ids()
+        .map(|id| engine.container(id).pressure())
+        .sum();
+    let stats = engine.stats();
+    println!(
+        "{} solver, {}x{} grid: {} containers, {} pipes, {} reactions",
+        if implicit { "implicit" } else { "explicit" },
+        grid_size,
+        grid_size,
+        stats.container_count,
+        stats.pipe_count,
+        stats.reaction_count
+    );
+    println!(
+        "{} ticks in {:.3} ms (best of {}): {:.1} ticks/sec, checksum {}",
+        ticks,
+        best.as_secs_f64() * 1000.0,
+        RUNS,
+        ticks_per_second,
+        checksum
+    );
+}
+
+// A square grid of rooms under one root, each piped to its right and lower neighbour. Every
+// few rooms hold a crew member and their food, so reactions run alongside the pipe flow.
+fn grid_engine(size: usize) -> Engine {
+    let mut engine = Engine::new(
+        Volume::new((size * size * 100) as i64),
+        Gas::zero(),
+        Fluid::zero(),
+        Solid::zero(),
+    );
+    let root = engine.root();
+    let mut rooms: Vec<ContainerId> = Vec::with_capacity(size * size);
+    for index in 0..size * size {
+        let o2 = 20_000 + (index % 7) as i64 * 1_000;
+        let crewed = index % 5 == 0;
+        let food = Solid {
+            ch2o: if crewed { CREW_FOOD } else { 0 },
+            ice: 0,
+        };
+        let room = engine.add_container(
+            root,
+            Volume::new(100),
+            Gas {
+                o2,
+                co2: 100,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid::zero(),
+            food,
+        );
+        if crewed {
+            add_human(&mut engine, room, 3);
+        }
+        rooms.push(room);
+    }
+
+    let flow_rate = Gas {
+        o2: 50,
+        co2: 50,
+        co: 50,
+        h2o: 50,
+    };
+    for y in 0..size {
+        for x in 0..size {
+            let room = rooms[y * size + x];
+            if x + 1 < size {
+                engine.add_pipe(room, rooms[y * size + x + 1], flow_rate);
+            }
+            if y + 1 < size {
+                engine.add_pipe(room, rooms[(y + 1) * size + x], flow_rate);
+            }
+        }
+    }
+    engine
+}
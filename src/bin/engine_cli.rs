@@ -1,23 +1,116 @@
 use dustfall::engine::{
-    add_human, add_moxie, add_photosynthesis, gas_from_parts, Engine, Fluid, Gas, Solid, Volume,
+    add_human, add_moxie, add_photosynthesis, gas_from_parts, ContainerId, Engine, Fluid, Gas,
+    Solid, Species, Volume,
 };
+use dustfall::scene::Scene;
 use dustfall::units::PressureScale;
 
 fn thin_atmosphere(volume: Volume, pressure: i64) -> Gas {
-    // The reported composition is a volume (molar) ratio, so we treat it as mole fractions.
+    // Mole fractions measured by Curiosity's SAM instrument (Franz et al., 2017): CO2 95.1%,
+    // N2 2.59%, Ar 1.94%, O2 0.161% and CO 0.058%. The engine has no N2 or Ar species yet, so
+    // those 453 parts are left out: the modelled partial pressures are right, and the total
+    // comes out about 4.5% low. A finer divisor would overflow i64 at planetary volumes.
     const DIVISOR: i64 = 10_000;
-    const CO2_PARTS: i64 = 9_532;
-    const O2_PARTS: i64 = 13;
-    gas_from_parts(volume, pressure, O2_PARTS, CO2_PARTS, 0, DIVISOR)
+    const CO2_PARTS: i64 = 9_510;
+    const O2_PARTS: i64 = 16;
+    const CO_PARTS: i64 = 6;
+    let mut gas = gas_from_parts(volume, pressure, O2_PARTS, CO2_PARTS, 0, DIVISOR);
+    gas.co = pressure * volume.value() * CO_PARTS / DIVISOR;
+    gas
 }
 
+// usage: engine_cli [--verbose] [ticks [scene.json]]
 fn main() {
-    let ticks: usize = std::env::args()
-        .nth(1)
+    let (flags, args): (Vec<String>, Vec<String>) = std::env::args()
+        .skip(1)
+        .partition(|arg| arg.starts_with("--"));
+    let verbose = flags.iter().any(|flag| flag == "--verbose");
+    let ticks: usize = args
+        .first()
         .and_then(|value| value.parse().ok())
         .unwrap_or(10);
-
     let scale = PressureScale::new(100.0);
+    let mut engine = match args.get(1) {
+        Some(path) => load_scene(path),
+        None => default_engine(scale),
+    };
+
+    let label = |id| {
+        engine
+            .container(id)
+            .label()
+            .unwrap_or("unlabeled")
+            .to_owned()
+    };
+    let tracked: Vec<_> = engine.container_ids().map(|id| (id, label(id))).collect();
+    for tick in 0..ticks {
+        let pressures: Vec<String> = tracked
+            .iter()
+            .map(|(id, label)| {
+                let kpa = scale.to_pascal(engine.container(*id).pressure()) / 1000.0;
+                format!("{}={:.2} kPa", label, kpa)
+            })
+            .collect();
+        println!("tick {}: {}", tick, pressures.join(", "));
+        if verbose {
+            print_species_table(&engine, &tracked, scale);
+        }
+        engine.tick();
+    }
+    for (id, label) in tracked.iter().skip(1) {
+        println!(
+            "{} after {} ticks:\n{}",
+            label,
+            ticks,
+            engine.container(*id).describe(scale)
+        );
+    }
+}
+
+// Partial pressure and mole fraction of every species, one aligned row per container.
+fn print_species_table(engine: &Engine, tracked: &[(ContainerId, String)], scale: PressureScale) {
+    let label_width = tracked
+        .iter()
+        .map(|(_, label)| label.len())
+        .max()
+        .unwrap_or(0);
+    let mut header = format!("  {:label_width$}", "");
+    for species in Species::ALL {
+        header += &format!(" {:>10} {:>7}", format!("{} kPa", species.symbol()), "mol%");
+    }
+    println!("{}", header);
+
+    for (id, label) in tracked {
+        let container = engine.container(*id);
+        let gas = container.gas();
+        let mut row = format!("  {:label_width$}", label);
+        for (species, pressure) in container.gas_pressure_breakdown() {
+            let fraction = gas.fraction(species);
+            row += &format!(
+                " {:>10.2} {:>6.2}%",
+                scale.to_pascal(pressure) / 1000.0,
+                fraction * 100.0
+            );
+        }
+        println!("{}", row);
+    }
+}
+
+fn load_scene(path: &str) -> Engine {
+    let scene = std::fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|json| Scene::from_json(&json).map_err(|error| error.to_string()))
+        .and_then(|scene| Engine::from_scene(&scene).map_err(|error| error.to_string()));
+    match scene {
+        Ok(engine) => engine,
+        Err(error) => {
+            eprintln!("{}: {}", path, error);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn default_engine(scale: PressureScale) -> Engine {
     let atmosphere_volume = Volume::new(93_000_000_000_000);
     let mut engine = Engine::new(
         atmosphere_volume,
@@ -26,18 +119,22 @@ fn main() {
         Solid::zero(),
     );
     let root = engine.root();
-    let habitat = engine.add_container(
-        root,
-        Volume::new(100),
-        Gas {
-            o2: 20_200,
-            co2: 80_800,
-            co: 0,
-            h2o: 0,
-        },
-        Fluid::zero(),
-        Solid { ch2o: 500 },
-    );
+    engine.set_name(root, "atmosphere").unwrap();
+    let habitat = engine
+        .add_named_container(
+            root,
+            "habitat",
+            Volume::new(100),
+            Gas {
+                o2: 20_200,
+                co2: 80_800,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid::zero(),
+            Solid { ch2o: 500, ice: 0 },
+        )
+        .unwrap();
     // Vent CO from the habitat back into the atmosphere through a CO-only pipe.
     engine.add_pipe(
         habitat,
@@ -52,14 +149,5 @@ fn main() {
     add_human(&mut engine, habitat, 3);
     add_photosynthesis(&mut engine, habitat, 2);
     add_moxie(&mut engine, habitat, 2);
-
-    for tick in 0..ticks {
-        println!(
-            "tick {}: atmosphere={:.2} kPa, habitat={:.2} kPa",
-            tick,
-            scale.to_pascal(engine.container(root).pressure()) / 1000.0,
-            scale.to_pascal(engine.container(habitat).pressure()) / 1000.0
-        );
-        engine.tick();
-    }
+    engine
 }
@@ -0,0 +1,51 @@
+use dustfall::solar::{self, Location, PlanetParameters, PLANET_NAMES};
+
+const SAMPLES_PER_SOL: usize = 24;
+// Gale crater, where the main app's sun is computed too.
+const LOCATION: Location = Location {
+    latitude: 22.5 * (std::f32::consts::TAU / 360.0),
+    longitude: 137.4 * (std::f32::consts::TAU / 360.0),
+};
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: mars_sun_cli [--planet {}] [start_seconds]",
+        PLANET_NAMES.join("|")
+    );
+    std::process::exit(2);
+}
+
+// Prints the sun's elevation and azimuth at evenly spaced times over one solar day.
+fn main() {
+    let mut planet = solar::MARS;
+    let mut start = 0.0f32;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--planet" => {
+                planet = args
+                    .next()
+                    .and_then(|name| solar::planet_by_name(&name))
+                    .unwrap_or_else(|| usage());
+            }
+            // Negative start times are before the unix epoch.
+            value => start = value.parse().unwrap_or_else(|_| usage()),
+        }
+    }
+    print_sol(&planet, start);
+}
+
+fn print_sol(planet: &PlanetParameters, start: f32) {
+    for sample in 0..SAMPLES_PER_SOL {
+        let time = start + planet.sol_seconds * sample as f32 / SAMPLES_PER_SOL as f32;
+        let angles = solar::solar_angles(planet, LOCATION, time);
+        let hours = planet.local_mean_solar_time_hours(time, LOCATION.longitude);
+        println!(
+            "{:5.2} h: elevation {:6.1} deg, azimuth {:5.1} deg, {:?}",
+            hours,
+            angles.elevation.to_degrees(),
+            angles.azimuth.to_degrees(),
+            solar::twilight_phase(angles.elevation)
+        );
+    }
+}
@@ -0,0 +1,410 @@
+use crate::engine::{ContainerId, Engine, Fluid, Gas, ReactionError, Solid, Volume};
+use std::fmt;
+
+// Collects containers, pipes and reactions, then checks every reference at once in `build`
+// instead of panicking part-way through construction. Container ids handed out here are the
+// ids the built engine uses.
+#[derive(Debug, Clone)]
+pub struct EngineBuilder {
+    containers: Vec<ContainerSpec>,
+    pipes: Vec<(ContainerId, ContainerId, Gas)>,
+    reactions: Vec<(ContainerId, Gas, Fluid, Solid)>,
+}
+
+#[derive(Debug, Clone)]
+struct ContainerSpec {
+    parent: Option<ContainerId>,
+    label: Option<String>,
+    name: Option<String>,
+    volume: Volume,
+    gas: Gas,
+    fluid: Fluid,
+    solid: Solid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    UnknownContainer(ContainerId),
+    DuplicateName(String),
+    InvalidContainer {
+        id: ContainerId,
+        reason: &'static str,
+    },
+    InvalidPipe {
+        a: ContainerId,
+        b: ContainerId,
+        reason: &'static str,
+    },
+    // The engine refused the reaction; `error` keeps the residual atom counts.
+    RejectedReaction {
+        container: ContainerId,
+        error: ReactionError,
+    },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::UnknownContainer(id) => {
+                write!(f, "container {} was never declared", id.index())
+            }
+            BuildError::DuplicateName(name) => {
+                write!(f, "container name \"{name}\" is used more than once")
+            }
+            BuildError::InvalidContainer { id, reason } => {
+                write!(f, "container {}: {reason}", id.index())
+            }
+            BuildError::InvalidPipe { a, b, reason } => {
+                write!(f, "pipe {}-{}: {reason}", a.index(), b.index())
+            }
+            BuildError::RejectedReaction { container, error } => {
+                write!(f, "reaction in container {}: {error}", container.index())
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl EngineBuilder {
+    pub fn new(volume: Volume, gas: Gas, fluid: Fluid, solid: Solid) -> Self {
+        Self {
+            containers: vec![ContainerSpec {
+                parent: None,
+                label: None,
+                name: None,
+                volume,
+                gas,
+                fluid,
+                solid,
+            }],
+            pipes: Vec::new(),
+            reactions: Vec::new(),
+        }
+    }
+
+    pub fn root(&self) -> ContainerId {
+        ContainerId::from_index(0)
+    }
+
+    pub fn container(
+        &mut self,
+        parent: ContainerId,
+        volume: Volume,
+        gas: Gas,
+        fluid: Fluid,
+        solid: Solid,
+    ) -> ContainerId {
+        self.containers.push(ContainerSpec {
+            parent: Some(parent),
+            label: None,
+            name: None,
+            volume,
+            gas,
+            fluid,
+            solid,
+        });
+        ContainerId::from_index(self.containers.len() - 1)
+    }
+
+    // Starts an empty container under the root, named as by `Engine::set_name`. Chain the
+    // `with_*` and `child_of` calls to fill it in, then take its `id`.
+    pub fn named_container(&mut self, name: &str, volume: Volume) -> ContainerBuilder<'_> {
+        let root = self.root();
+        let id = self.container(root, volume, Gas::zero(), Fluid::zero(), Solid::zero());
+        self.containers[id.index()].name = Some(name.to_owned());
+        ContainerBuilder { builder: self, id }
+    }
+
+    // Unknown ids are reported by `build`, like every other bad reference.
+    pub fn label(&mut self, id: ContainerId, label: &str) -> &mut Self {
+        if let Some(spec) = self.containers.get_mut(id.index()) {
+            spec.label = Some(label.to_owned());
+        }
+        self
+    }
+
+    pub fn pipe(&mut self, a: ContainerId, b: ContainerId, flow_rate: Gas) -> &mut Self {
+        self.pipes.push((a, b, flow_rate));
+        self
+    }
+
+    pub fn reaction(
+        &mut self,
+        container: ContainerId,
+        gas_delta: Gas,
+        fluid_delta: Fluid,
+        solid_delta: Solid,
+    ) -> &mut Self {
+        self.reactions
+            .push((container, gas_delta, fluid_delta, solid_delta));
+        self
+    }
+
+    pub fn build(&self) -> Result<Engine, BuildError> {
+        let declared = |id: ContainerId| {
+            if id.index() < self.containers.len() {
+                Ok(id)
+            } else {
+                Err(BuildError::UnknownContainer(id))
+            }
+        };
+
+        for (index, spec) in self.containers.iter().enumerate() {
+            let id = ContainerId::from_index(index);
+            let out_of_order = spec.parent.is_some_and(|parent| parent.index() >= index);
+            let reason = container_problem(spec.volume, spec.gas, spec.fluid, spec.solid)
+                .or(out_of_order.then_some("parent must be declared before the container"));
+            if let Some(reason) = reason {
+                return Err(BuildError::InvalidContainer { id, reason });
+            }
+            if let Some(name) = &spec.name {
+                let earlier = &self.containers[..index];
+                if earlier
+                    .iter()
+                    .any(|other| other.name.as_ref() == Some(name))
+                {
+                    return Err(BuildError::DuplicateName(name.clone()));
+                }
+            }
+        }
+        for &(a, b, flow_rate) in &self.pipes {
+            let (a, b) = (declared(a)?, declared(b)?);
+            if let Some(reason) = pipe_problem(a, b, flow_rate) {
+                return Err(BuildError::InvalidPipe { a, b, reason });
+            }
+        }
+        for &(container, ..) in &self.reactions {
+            declared(container)?;
+        }
+
+        let (root, children) = self
+            .containers
+            .split_first()
+            .expect("the builder always has a root");
+        let mut engine = Engine::new(root.volume, root.gas, root.fluid, root.solid);
+        for spec in children {
+            let parent = spec.parent.expect("only the root has no parent");
+            engine.add_container(parent, spec.volume, spec.gas, spec.fluid, spec.solid);
+        }
+        for (index, spec) in self.containers.iter().enumerate() {
+            let id = ContainerId::from_index(index);
+            if let Some(name) = &spec.name {
+                engine
+                    .set_name(id, name)
+                    .expect("names were checked to be unique");
+            }
+            if let Some(label) = &spec.label {
+                engine.set_label(id, label);
+            }
+        }
+        for &(a, b, flow_rate) in &self.pipes {
+            engine.add_pipe(a, b, flow_rate);
+        }
+        for &(container, gas, fluid, solid) in &self.reactions {
+            engine
+                .try_add_reaction(container, gas, fluid, solid)
+                .map_err(|error| BuildError::RejectedReaction { container, error })?;
+        }
+        Ok(engine)
+    }
+}
+
+// Checks shared by `EngineBuilder::build` and `Engine::from_scene`, so both reject the same
+// containers and pipes with the same reasons.
+pub(crate) fn container_problem(
+    volume: Volume,
+    gas: Gas,
+    fluid: Fluid,
+    solid: Solid,
+) -> Option<&'static str> {
+    let negative = fluid.h2o < 0 || solid.ch2o < 0 || solid.ice < 0;
+    if volume.value() <= 0 {
+        Some("volume must be positive")
+    } else if negative || !gas.is_non_negative() {
+        Some("amounts must be non-negative")
+    } else {
+        None
+    }
+}
+
+pub(crate) fn pipe_problem(a: ContainerId, b: ContainerId, flow_rate: Gas) -> Option<&'static str> {
+    if a == b {
+        Some("endpoints must be different")
+    } else if !flow_rate.is_non_negative() {
+        Some("flow rates must be non-negative")
+    } else {
+        None
+    }
+}
+
+// One container being declared by `EngineBuilder::named_container`.
+pub struct ContainerBuilder<'a> {
+    builder: &'a mut EngineBuilder,
+    id: ContainerId,
+}
+
+impl ContainerBuilder<'_> {
+    pub fn with_gas(self, gas: Gas) -> Self {
+        self.builder.containers[self.id.index()].gas = gas;
+        self
+    }
+
+    pub fn with_fluid(self, fluid: Fluid) -> Self {
+        self.builder.containers[self.id.index()].fluid = fluid;
+        self
+    }
+
+    pub fn with_solid(self, solid: Solid) -> Self {
+        self.builder.containers[self.id.index()].solid = solid;
+        self
+    }
+
+    // Containers start under the root; the parent must be declared before this container.
+    pub fn child_of(self, parent: ContainerId) -> Self {
+        self.builder.containers[self.id.index()].parent = Some(parent);
+        self
+    }
+
+    pub fn id(self) -> ContainerId {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{add_human, add_moxie, add_photosynthesis};
+
+    fn gas(o2: i64, co2: i64) -> Gas {
+        Gas {
+            o2,
+            co2,
+            co: 0,
+            h2o: 0,
+        }
+    }
+
+    #[test]
+    fn builder_matches_imperative_construction() {
+        let (fluid, solid) = (Fluid::zero(), Solid::zero());
+        let exhaled = Gas {
+            h2o: 3,
+            ..gas(-3, 3)
+        };
+        let human = (exhaled, Fluid::zero(), Solid { ch2o: -3, ice: 0 });
+
+        let mut builder = EngineBuilder::new(Volume::new(1000), gas(0, 9000), fluid, solid);
+        let root = builder.root();
+        let habitat = builder.container(root, Volume::new(10), gas(200, 800), fluid, solid);
+        builder
+            .label(habitat, "habitat")
+            .pipe(habitat, root, gas(1, 1))
+            .reaction(habitat, human.0, human.1, human.2);
+        let built = builder.build().unwrap();
+
+        let mut expected = Engine::new(Volume::new(1000), gas(0, 9000), fluid, solid);
+        let id = expected.add_container(root, Volume::new(10), gas(200, 800), fluid, solid);
+        expected.set_label(id, "habitat");
+        expected.add_pipe(id, root, gas(1, 1));
+        add_human(&mut expected, id, 3);
+        assert_eq!(built, expected);
+    }
+
+    #[test]
+    fn builder_declares_the_engine_cli_scenario_by_name() {
+        let atmosphere = Gas {
+            co: 6,
+            ..gas(16, 9510)
+        };
+        let habitat_air = gas(20_200, 80_800);
+        let food = Solid { ch2o: 500, ice: 0 };
+        let co_vent = Gas { co: 2, ..gas(0, 0) };
+
+        let mut builder = EngineBuilder::new(
+            Volume::new(1_000_000),
+            atmosphere,
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        let root = builder.root();
+        builder.label(root, "atmosphere");
+        let habitat = builder
+            .named_container("habitat", Volume::new(100))
+            .with_gas(habitat_air)
+            .with_solid(food)
+            .child_of(root)
+            .id();
+        let crew = (
+            Gas {
+                h2o: 3,
+                ..gas(-3, 3)
+            },
+            Solid { ch2o: -3, ice: 0 },
+        );
+        let plants = (gas(2, -2), Fluid { h2o: -2 }, Solid { ch2o: 2, ice: 0 });
+        let moxie = Gas {
+            co: 2,
+            ..gas(1, -2)
+        };
+        builder
+            .pipe(habitat, root, co_vent)
+            .reaction(habitat, crew.0, Fluid::zero(), crew.1)
+            .reaction(habitat, plants.0, plants.1, plants.2)
+            .reaction(habitat, moxie, Fluid::zero(), Solid::zero());
+        let built = builder.build().unwrap();
+        assert_eq!(built.container_by_name("habitat"), Some(habitat));
+
+        let (fluid, solid) = (Fluid::zero(), Solid::zero());
+        let mut expected = Engine::new(Volume::new(1_000_000), atmosphere, fluid, solid);
+        expected.set_label(root, "atmosphere");
+        let id = expected
+            .add_named_container(root, "habitat", Volume::new(100), habitat_air, fluid, food)
+            .unwrap();
+        expected.add_pipe(id, root, co_vent);
+        add_human(&mut expected, id, 3);
+        add_photosynthesis(&mut expected, id, 2);
+        add_moxie(&mut expected, id, 2);
+        assert_eq!(built, expected);
+
+        builder.named_container("habitat", Volume::new(5));
+        assert_eq!(
+            builder.build().unwrap_err().to_string(),
+            "container name \"habitat\" is used more than once"
+        );
+    }
+
+    #[test]
+    fn builder_rejects_undeclared_containers_and_bad_pipes() {
+        let (fluid, solid) = (Fluid::zero(), Solid::zero());
+        let mut builder = EngineBuilder::new(Volume::new(10), Gas::zero(), fluid, solid);
+        let root = builder.root();
+        let stranger = ContainerId::from_index(5);
+        builder.pipe(root, stranger, Gas::zero());
+        assert_eq!(builder.build(), Err(BuildError::UnknownContainer(stranger)));
+
+        let mut builder = EngineBuilder::new(Volume::new(10), Gas::zero(), fluid, solid);
+        let root = builder.root();
+        builder.pipe(root, root, Gas::zero());
+        assert_eq!(
+            builder.build().unwrap_err().to_string(),
+            "pipe 0-0: endpoints must be different"
+        );
+
+        let mut builder = EngineBuilder::new(Volume::new(10), Gas::zero(), fluid, solid);
+        let root = builder.root();
+        builder.reaction(root, gas(1, 0), fluid, solid);
+        let error = ReactionError::Unbalanced {
+            carbon: 0,
+            hydrogen: 0,
+            oxygen: 2,
+        };
+        assert_eq!(
+            builder.build(),
+            Err(BuildError::RejectedReaction {
+                container: root,
+                error
+            })
+        );
+    }
+}
@@ -1,3 +1,13 @@
+use crate::solar::{irradiance_fraction, SolarAngles};
+use crate::units::{
+    PressureScale, MOLAR_MASS_CH2O, MOLAR_MASS_CO, MOLAR_MASS_CO2, MOLAR_MASS_H2O, MOLAR_MASS_O2,
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::ops::{Add, Mul};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ContainerId(usize);
 
@@ -5,6 +15,32 @@ impl ContainerId {
     pub fn index(self) -> usize {
         self.0
     }
+
+    // Containers are numbered in creation order, which lets builders hand out ids up front.
+    pub(crate) fn from_index(index: usize) -> Self {
+        Self(index)
+    }
+}
+
+// Reactions are numbered in the order they were added, which is also the order they run in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReactionId(usize);
+
+impl ReactionId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+// Pipes are numbered in the order they were added, matching `Engine::pipes`. Merging
+// containers drops pipes that would loop back on themselves, renumbering the ones after them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PipeId(usize);
+
+impl PipeId {
+    pub fn index(self) -> usize {
+        self.0
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -21,6 +57,44 @@ impl Volume {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Species {
+    O2,
+    Co2,
+    Co,
+    H2o,
+}
+
+impl Species {
+    pub const COUNT: usize = 4;
+    pub const ALL: [Species; Species::COUNT] =
+        [Species::O2, Species::Co2, Species::Co, Species::H2o];
+
+    // Position in `ALL`, and in a `GasMix`'s amounts.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Species::O2 => "O2",
+            Species::Co2 => "CO2",
+            Species::Co => "CO",
+            Species::H2o => "H2O",
+        }
+    }
+
+    // Grams per mole.
+    pub fn molar_mass(self) -> i64 {
+        match self {
+            Species::O2 => MOLAR_MASS_O2,
+            Species::Co2 => MOLAR_MASS_CO2,
+            Species::Co => MOLAR_MASS_CO,
+            Species::H2o => MOLAR_MASS_H2O,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 // Amounts are in integer "moles" (amount-of-substance units), not mass.
 pub struct Gas {
     // These amounts drive partial pressure when divided by volume.
@@ -40,6 +114,24 @@ impl Gas {
         }
     }
 
+    pub fn amount(&self, species: Species) -> i64 {
+        match species {
+            Species::O2 => self.o2,
+            Species::Co2 => self.co2,
+            Species::Co => self.co,
+            Species::H2o => self.h2o,
+        }
+    }
+
+    fn amount_mut(&mut self, species: Species) -> &mut i64 {
+        match species {
+            Species::O2 => &mut self.o2,
+            Species::Co2 => &mut self.co2,
+            Species::Co => &mut self.co,
+            Species::H2o => &mut self.h2o,
+        }
+    }
+
     pub fn is_non_negative(&self) -> bool {
         self.o2 >= 0 && self.co2 >= 0 && self.co >= 0 && self.h2o >= 0
     }
@@ -55,6 +147,37 @@ impl Gas {
             + Self::partial_pressure(self.h2o, volume)
     }
 
+    // Divides once after summing, so it floors at most one unit instead of one per species.
+    pub fn pressure_exact(&self, volume: Volume) -> i64 {
+        self.total() / volume.value()
+    }
+
+    // All species together, in moles.
+    pub fn total(&self) -> i64 {
+        self.o2 + self.co2 + self.co + self.h2o
+    }
+
+    pub fn species_mass_grams(&self, species: Species) -> i64 {
+        self.amount(species) * species.molar_mass()
+    }
+
+    pub fn mass_grams(&self) -> i64 {
+        Species::ALL
+            .iter()
+            .map(|&species| self.species_mass_grams(species))
+            .sum()
+    }
+
+    // Mole fraction of `species`, or 0 when there is no gas at all.
+    pub fn fraction(&self, species: Species) -> f32 {
+        let total = self.total();
+        if total > 0 {
+            (self.amount(species) as f64 / total as f64) as f32
+        } else {
+            0.0
+        }
+    }
+
     pub fn can_apply_delta(&self, delta: Gas) -> bool {
         self.o2 + delta.o2 >= 0
             && self.co2 + delta.co2 >= 0
@@ -68,6 +191,104 @@ impl Gas {
         self.co += delta.co;
         self.h2o += delta.h2o;
     }
+
+    // Multiplies every species by `num / den`, truncating each one toward zero. That floors
+    // amounts like `gas_from_parts` does, and never scales a negative delta past its share.
+    pub fn scale(self, num: i64, den: i64) -> Gas {
+        assert!(den > 0, "den must be positive");
+        Gas {
+            o2: self.o2 * num / den,
+            co2: self.co2 * num / den,
+            co: self.co * num / den,
+            h2o: self.h2o * num / den,
+        }
+    }
+}
+
+impl Add for Gas {
+    type Output = Gas;
+
+    fn add(mut self, other: Gas) -> Gas {
+        self.apply_delta(other);
+        self
+    }
+}
+
+impl Mul<i64> for Gas {
+    type Output = Gas;
+
+    fn mul(self, factor: i64) -> Gas {
+        Gas {
+            o2: self.o2 * factor,
+            co2: self.co2 * factor,
+            co: self.co * factor,
+            h2o: self.h2o * factor,
+        }
+    }
+}
+
+// The same amounts as `Gas`, stored per `Species` so code can loop over species instead of
+// naming fields. Converts losslessly to and from `Gas` while the engine migrates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct GasMix {
+    amounts: [i64; Species::COUNT],
+}
+
+impl GasMix {
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    pub fn amount(&self, species: Species) -> i64 {
+        self.amounts[species.index()]
+    }
+
+    pub fn set_amount(&mut self, species: Species, amount: i64) {
+        self.amounts[species.index()] = amount;
+    }
+
+    pub fn is_non_negative(&self) -> bool {
+        self.amounts.iter().all(|&amount| amount >= 0)
+    }
+
+    // Floors each species' partial pressure, like `Gas::pressure`.
+    pub fn pressure(&self, volume: Volume) -> i64 {
+        self.amounts
+            .iter()
+            .map(|&amount| Gas::partial_pressure(amount, volume))
+            .sum()
+    }
+
+    pub fn can_apply_delta(&self, delta: GasMix) -> bool {
+        self.amounts
+            .iter()
+            .zip(delta.amounts)
+            .all(|(&amount, delta)| amount + delta >= 0)
+    }
+
+    pub fn apply_delta(&mut self, delta: GasMix) {
+        for (amount, delta) in self.amounts.iter_mut().zip(delta.amounts) {
+            *amount += delta;
+        }
+    }
+}
+
+impl From<Gas> for GasMix {
+    fn from(gas: Gas) -> Self {
+        Self {
+            amounts: Species::ALL.map(|species| gas.amount(species)),
+        }
+    }
+}
+
+impl From<GasMix> for Gas {
+    fn from(mix: GasMix) -> Self {
+        let mut gas = Gas::zero();
+        for species in Species::ALL {
+            *gas.amount_mut(species) = mix.amount(species);
+        }
+        gas
+    }
 }
 
 pub fn gas_from_parts(
@@ -95,10 +316,15 @@ pub fn gas_from_parts(
     let h2o = raw_h2o / divisor;
     // Note: We floor each component, so the sum can be slightly below the intended total.
 
-    Gas { o2, co2, co: 0, h2o }
+    Gas {
+        o2,
+        co2,
+        co: 0,
+        h2o,
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 pub struct Fluid {
     pub h2o: i64,
 }
@@ -115,43 +341,80 @@ impl Fluid {
     pub fn apply_delta(&mut self, delta: Fluid) {
         self.h2o += delta.h2o;
     }
+
+    pub fn mass_grams(&self) -> i64 {
+        self.h2o * MOLAR_MASS_H2O
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 pub struct Solid {
     pub ch2o: i64,
+    // Frozen water, made and melted by `FreezeThaw`.
+    #[serde(default)]
+    pub ice: i64,
 }
 
 impl Solid {
     pub fn zero() -> Self {
-        Self { ch2o: 0 }
+        Self { ch2o: 0, ice: 0 }
     }
 
     pub fn can_apply_delta(&self, delta: Solid) -> bool {
-        self.ch2o + delta.ch2o >= 0
+        self.ch2o + delta.ch2o >= 0 && self.ice + delta.ice >= 0
     }
 
     pub fn apply_delta(&mut self, delta: Solid) {
         self.ch2o += delta.ch2o;
+        self.ice += delta.ice;
+    }
+
+    pub fn mass_grams(&self) -> i64 {
+        self.ch2o * MOLAR_MASS_CH2O + self.ice * MOLAR_MASS_H2O
+    }
+
+    // Food and ice together, in moles.
+    pub fn total(&self) -> i64 {
+        self.ch2o + self.ice
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Container {
     volume: Volume,
     gas: Gas,
     fluid: Fluid,
     solid: Solid,
+    limits: ContainerLimits,
+    label: Option<String>,
+    light_level: i64,
+    // In `TemperatureScale` units; only phase changes read it.
+    temperature: i64,
+    // Pressure above which the container bursts at the end of a tick.
+    max_pressure: Option<i64>,
     children: Vec<ContainerId>,
 }
 
+// Capacity caps, e.g. a finite water tank. `max_solid` covers food and ice together. Reactions
+// slow down and freezing stops to fit under a cap rather than overfilling it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ContainerLimits {
+    pub max_fluid: Option<i64>,
+    pub max_solid: Option<i64>,
+}
+
 impl Container {
-    fn new(volume: Volume, gas: Gas, fluid: Fluid, solid: Solid) -> Self {
+    fn new(volume: Volume, gas: Gas, fluid: Fluid, solid: Solid, limits: ContainerLimits) -> Self {
         Self {
             volume,
             gas,
             fluid,
             solid,
+            limits,
+            label: None,
+            light_level: FULL_LIGHT,
+            temperature: 0,
+            max_pressure: None,
             children: Vec::new(),
         }
     }
@@ -159,61 +422,622 @@ impl Container {
     pub fn pressure(&self) -> i64 {
         self.gas.pressure(self.volume)
     }
+
+    pub fn volume(&self) -> Volume {
+        self.volume
+    }
+
+    // Each species' partial pressure, in `Species::ALL` order.
+    pub fn gas_pressure_breakdown(&self) -> [(Species, i64); Species::COUNT] {
+        Species::ALL.map(|species| {
+            let pressure = Gas::partial_pressure(self.gas.amount(species), self.volume);
+            (species, pressure)
+        })
+    }
+
+    pub fn gas(&self) -> Gas {
+        self.gas
+    }
+
+    // Gas, fluid and solid together.
+    pub fn mass_grams(&self) -> i64 {
+        self.gas.mass_grams() + self.fluid.mass_grams() + self.solid.mass_grams()
+    }
+
+    pub fn fluid(&self) -> Fluid {
+        self.fluid
+    }
+
+    pub fn solid(&self) -> Solid {
+        self.solid
+    }
+
+    pub fn limits(&self) -> ContainerLimits {
+        self.limits
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn light_level(&self) -> i64 {
+        self.light_level
+    }
+
+    pub fn temperature(&self) -> i64 {
+        self.temperature
+    }
+
+    pub fn max_pressure(&self) -> Option<i64> {
+        self.max_pressure
+    }
+
+    // Multi-line human-readable summary, with pressures in kPa.
+    pub fn describe(&self, pressure_scale: PressureScale) -> String {
+        let kpa = |pressure: i64| pressure_scale.to_pascal(pressure) / 1000.0;
+        let mut report = format!("volume: {}\n", self.volume.value());
+        let _ = writeln!(report, "pressure: {:.2} kPa", kpa(self.pressure()));
+        for (species, pressure) in self.gas_pressure_breakdown() {
+            let _ = writeln!(report, "  {}: {:.2} kPa", species.symbol(), kpa(pressure));
+        }
+        let o2_fraction = self.gas.fraction(Species::O2);
+        let _ = writeln!(report, "O2 mole fraction: {:.1}%", o2_fraction * 100.0);
+        let _ = writeln!(report, "fluid H2O: {}", self.fluid.h2o);
+        let _ = writeln!(report, "solid CH2O: {}", self.solid.ch2o);
+        let _ = write!(report, "ice: {}", self.solid.ice);
+        report
+    }
+
+    // Amounts in the same order as `Reaction::components`.
+    fn amounts(&self) -> [i64; 7] {
+        [
+            self.gas.o2,
+            self.gas.co2,
+            self.gas.co,
+            self.gas.h2o,
+            self.fluid.h2o,
+            self.solid.ch2o,
+            self.solid.ice,
+        ]
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Pipe {
     pub a: ContainerId,
     pub b: ContainerId,
-    // Flow rate per tick, expressed as moles of each gas.
+    // Flow rate per tick from `a` to `b`, expressed as moles of each gas. `tick` moves at most
+    // this much toward equal partial pressures. `tick_implicit` instead reads it as a
+    // conductance, moving this many moles per tick per unit of partial pressure difference, so
+    // the same pipe carries far more there.
     pub flow_rate: Gas,
+    // Flow rate per tick from `b` back to `a`, e.g. a check valve or restrictor.
+    pub reverse_flow_rate: Gas,
+    // A closed valve stops all flow; pipes start open.
+    pub open: bool,
 }
 
 impl Pipe {
     pub fn new(a: ContainerId, b: ContainerId, flow_rate: Gas) -> Self {
-        assert!(flow_rate.is_non_negative(), "flow rates must be non-negative");
-        Self { a, b, flow_rate }
+        Self::asymmetric(a, b, flow_rate, flow_rate)
+    }
+
+    pub fn asymmetric(
+        a: ContainerId,
+        b: ContainerId,
+        flow_rate: Gas,
+        reverse_flow_rate: Gas,
+    ) -> Self {
+        assert!(
+            flow_rate.is_non_negative(),
+            "flow rates must be non-negative"
+        );
+        assert!(
+            reverse_flow_rate.is_non_negative(),
+            "flow rates must be non-negative"
+        );
+        Self {
+            a,
+            b,
+            flow_rate,
+            reverse_flow_rate,
+            open: true,
+        }
+    }
+}
+
+// Which end of a pipe a container sits on; flow from `A` to `B` uses `flow_rate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PipeEnd {
+    A,
+    B,
+}
+
+// Moves up to `rate` moles of each gas from `from` to `to` every tick, regardless of pressure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pump {
+    pub from: ContainerId,
+    pub to: ContainerId,
+    pub rate: Gas,
+}
+
+impl Pump {
+    pub fn new(from: ContainerId, to: ContainerId, rate: Gas) -> Self {
+        assert!(rate.is_non_negative(), "pump rates must be non-negative");
+        Self { from, to, rate }
+    }
+}
+
+// Discards up to `rate` moles of each gas to vacuum every tick, like a micrometeorite puncture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Leak {
+    pub container: ContainerId,
+    pub rate: Gas,
+}
+
+// Freezes all of a container's fluid water into ice while it is colder than
+// `freeze_temperature`, and melts the ice back once it is at or above it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreezeThaw {
+    pub container: ContainerId,
+    pub freeze_temperature: i64,
+}
+
+// Warning thresholds in pressure units, compared against partial pressures (and the total for
+// `pressure_min`). `None` leaves that check off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Alarm {
+    pub o2_min: Option<i64>,
+    pub co2_max: Option<i64>,
+    pub pressure_min: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlarmCondition {
+    LowO2,
+    HighCo2,
+    LowPressure,
+}
+
+// A container found over its `max_pressure` at the end of a tick, with the pressure it reached
+// before venting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BurstEvent {
+    pub container: ContainerId,
+    pub pressure: i64,
+}
+
+// A burst vents this fraction of each species into the root.
+const BURST_VENT_NUM: i64 = 1;
+const BURST_VENT_DEN: i64 = 2;
+
+// One failed check; `alarm` indexes `Engine::alarms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TrippedAlarm {
+    pub alarm: usize,
+    pub container: ContainerId,
+    pub condition: AlarmCondition,
+}
+
+// Only carbon, hydrogen and oxygen are counted, since every species is built from them. A new
+// species needs its atoms added to `of`, and a new field here if it brings a new element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AtomCount {
+    pub carbon: i64,
+    pub hydrogen: i64,
+    pub oxygen: i64,
+}
+
+impl AtomCount {
+    pub fn zero() -> Self {
+        Self {
+            carbon: 0,
+            hydrogen: 0,
+            oxygen: 0,
+        }
+    }
+
+    pub fn of(gas: Gas, fluid: Fluid, solid: Solid) -> Self {
+        Self {
+            carbon: gas.co2 + gas.co + solid.ch2o,
+            hydrogen: 2 * (gas.h2o + fluid.h2o + solid.ch2o + solid.ice),
+            oxygen: 2 * gas.o2
+                + 2 * gas.co2
+                + gas.co
+                + gas.h2o
+                + fluid.h2o
+                + solid.ch2o
+                + solid.ice,
+        }
+    }
+
+    fn add(&mut self, other: AtomCount) {
+        self.carbon += other.carbon;
+        self.hydrogen += other.hydrogen;
+        self.oxygen += other.oxygen;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReactionMode {
+    // Skip the reaction for the tick if any reactant is short.
+    #[default]
+    AllOrNothing,
+    // Run at the largest fraction of the full rate that the container can supply.
+    Saturating,
+}
+
+// How a reaction's rate responds to its container's state. Under `ReactionScheduling::Sequential`
+// that is the state when the reaction runs, after earlier reactions this tick; under
+// `Simultaneous` it is the start-of-tick state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReactionRate {
+    // Always attempt the full rate.
+    #[default]
+    Constant,
+    // Scale the full rate by h / (h + p), where p is the species' partial pressure and h
+    // is `half_rate_pressure`, rounding down to whole balanced steps.
+    InhibitedBy {
+        species: Species,
+        half_rate_pressure: i64,
+    },
+    // Scale the full rate by the container's light level and by p / (h + p), where p is the
+    // partial pressure of the limiting species, e.g. CO2 for photosynthesis.
+    Lit {
+        species: Species,
+        half_rate_pressure: i64,
+    },
+}
+
+// A condition on the container's gas at the start of each run. Nothing it names is consumed,
+// so the reaction's own deltas stay atom-balanced on their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReactionRequirement {
+    // At least this much of every species is present, e.g. a catalyst.
+    AtLeast(Gas),
+    // The species' partial pressure is below `pressure`, e.g. a controller's set point.
+    PressureBelow { species: Species, pressure: i64 },
+}
+
+impl ReactionRequirement {
+    fn is_met(&self, container: &Container) -> bool {
+        match *self {
+            ReactionRequirement::AtLeast(gas) => Species::ALL
+                .iter()
+                .all(|&species| container.gas.amount(species) >= gas.amount(species)),
+            ReactionRequirement::PressureBelow { species, pressure } => {
+                Gas::partial_pressure(container.gas.amount(species), container.volume) < pressure
+            }
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReactionScheduling {
+    // Reactions run one after another in insertion order, each seeing the previous ones'
+    // results, so earlier reactions win when they compete for a reactant.
+    #[default]
+    Sequential,
+    // Every reaction is planned against the start-of-tick state, then shortages scale all
+    // competing reactions down by the same fraction. Products only become available next
+    // tick, and an `AllOrNothing` reaction may run partially when it has to share.
+    Simultaneous,
+}
+
+// How `Engine::tick` runs its pipes; `tick_implicit` always solves them together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PipeScheduling {
+    // Pipes run one after another in insertion order, each seeing the previous ones' flows.
+    // When several pipes drain the same container, earlier pipes win.
+    #[default]
+    Sequential,
+    // Every pipe's flow is computed from the start-of-tick state. When a container's outflows
+    // add up to more than it holds, they are all scaled down by the same fraction. Inflows are
+    // not capped, so a small container fed by several pipes at once can overshoot its
+    // neighbours where sequential flows would have settled. Opt in per engine.
+    Simultaneous,
+}
+
+// Light levels are in thousandths of full sunlight, see `Engine::set_light_level`.
+pub const FULL_LIGHT: i64 = 1000;
+
+// Diffusion rates are in thousandths of the way to equilibrium, see
+// `Engine::set_diffusion_rate`.
+pub const FULL_DIFFUSION: i64 = 1000;
+
+// Why `Engine::try_add_reaction` rejected a reaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReactionError {
+    UnknownContainer(ContainerId),
+    NonPositiveHalfRatePressure(i64),
+    // A negative `AtLeast` amount, which would always be met.
+    NegativeRequirement(Gas),
+    // Atoms the reaction would create per tick; negative counts are atoms it would destroy.
+    Unbalanced {
+        carbon: i64,
+        hydrogen: i64,
+        oxygen: i64,
+    },
+}
+
+impl fmt::Display for ReactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReactionError::UnknownContainer(id) => {
+                write!(f, "reaction container {} does not exist", id.index())
+            }
+            ReactionError::NonPositiveHalfRatePressure(pressure) => {
+                write!(f, "half_rate_pressure must be positive, got {pressure}")
+            }
+            ReactionError::NegativeRequirement(gas) => {
+                write!(f, "required amounts must be non-negative, got {gas:?}")
+            }
+            ReactionError::Unbalanced {
+                carbon,
+                hydrogen,
+                oxygen,
+            } => write!(
+                f,
+                "reaction is not atom-balanced (C {carbon:+}, H {hydrogen:+}, O {oxygen:+})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReactionError {}
+
+// Why `Engine::set_name` or `Engine::add_named_container` refused a name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameTaken {
+    pub name: String,
+    pub container: ContainerId,
+}
+
+impl fmt::Display for NameTaken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "name \"{}\" already belongs to container {}",
+            self.name,
+            self.container.index()
+        )
+    }
+}
+
+impl std::error::Error for NameTaken {}
+
+// A read-only copy of one reaction's full-rate deltas and settings, for listing in a UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReactionInfo {
+    pub gas_delta: Gas,
+    pub fluid_delta: Fluid,
+    pub solid_delta: Solid,
+    pub mode: ReactionMode,
+    pub rate: ReactionRate,
+    pub requirement: Option<ReactionRequirement>,
+}
+
+impl ReactionInfo {
+    // Signed non-zero deltas, consumed first, e.g. "-2 CO2, -2 H2O(l), +2 O2, +2 CH2O(s)".
+    pub fn summary(&self) -> String {
+        let mut terms: Vec<(i64, &str)> = Species::ALL
+            .iter()
+            .map(|&species| (self.gas_delta.amount(species), species.symbol()))
+            .collect();
+        terms.push((self.fluid_delta.h2o, "H2O(l)"));
+        terms.push((self.solid_delta.ch2o, "CH2O(s)"));
+        terms.push((self.solid_delta.ice, "ice"));
+        terms.retain(|&(amount, _)| amount != 0);
+        terms.sort_by_key(|&(amount, _)| amount > 0);
+        terms
+            .iter()
+            .map(|(amount, name)| format!("{amount:+} {name}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Reaction {
     container: ContainerId,
     gas_delta: Gas,
     fluid_delta: Fluid,
     solid_delta: Solid,
+    mode: ReactionMode,
+    rate: ReactionRate,
+    requirement: Option<ReactionRequirement>,
 }
 
 impl Reaction {
-    fn new(container: ContainerId, gas_delta: Gas, fluid_delta: Fluid, solid_delta: Solid) -> Self {
+    fn new(
+        container: ContainerId,
+        gas_delta: Gas,
+        fluid_delta: Fluid,
+        solid_delta: Solid,
+        mode: ReactionMode,
+        rate: ReactionRate,
+    ) -> Self {
         Self {
             container,
             gas_delta,
             fluid_delta,
             solid_delta,
+            mode,
+            rate,
+            requirement: None,
         }
     }
 
-    fn check(&self) -> bool {
-        let gas = self.gas_delta;
-        let fluid = self.fluid_delta;
-        let solid = self.solid_delta;
+    fn components(&self) -> [i64; 7] {
+        [
+            self.gas_delta.o2,
+            self.gas_delta.co2,
+            self.gas_delta.co,
+            self.gas_delta.h2o,
+            self.fluid_delta.h2o,
+            self.solid_delta.ch2o,
+            self.solid_delta.ice,
+        ]
+    }
+
+    // Dividing a balanced reaction by the gcd of its components keeps it balanced, so the
+    // full rate is `steps` whole repetitions of the smallest balanced step.
+    fn steps(&self) -> i64 {
+        self.components().into_iter().fold(0, gcd)
+    }
+
+    // Returns this reaction running `taken` of its `steps()` steps.
+    fn scaled(&self, taken: i64) -> Reaction {
+        let steps = self.steps();
+        let scale = |component: i64| component / steps * taken;
+        Reaction {
+            gas_delta: Gas {
+                o2: scale(self.gas_delta.o2),
+                co2: scale(self.gas_delta.co2),
+                co: scale(self.gas_delta.co),
+                h2o: scale(self.gas_delta.h2o),
+            },
+            fluid_delta: Fluid {
+                h2o: scale(self.fluid_delta.h2o),
+            },
+            solid_delta: Solid {
+                ch2o: scale(self.solid_delta.ch2o),
+                ice: scale(self.solid_delta.ice),
+            },
+            ..*self
+        }
+    }
+
+    // This tick's rate in steps, which may depend on the container's current gas.
+    fn rate_steps(&self, container: &Container) -> i64 {
+        let steps = self.steps();
+        match self.rate {
+            ReactionRate::Constant => steps,
+            ReactionRate::InhibitedBy {
+                species,
+                half_rate_pressure,
+            } => {
+                let amount = container.gas.amount(species);
+                let pressure = Gas::partial_pressure(amount, container.volume);
+                steps * half_rate_pressure / (half_rate_pressure + pressure)
+            }
+            ReactionRate::Lit {
+                species,
+                half_rate_pressure,
+            } => {
+                let amount = container.gas.amount(species);
+                let pressure = Gas::partial_pressure(amount, container.volume) as i128;
+                let numerator = steps as i128 * container.light_level as i128 * pressure;
+                let denominator = FULL_LIGHT as i128 * (half_rate_pressure as i128 + pressure);
+                (numerator / denominator) as i64
+            }
+        }
+    }
+
+    // The most steps the container's reactants can supply, capped at the full rate.
+    fn available_steps(&self, container: &Container) -> i64 {
+        let steps = self.steps();
+        self.components()
+            .into_iter()
+            .zip(container.amounts())
+            .filter(|&(component, _)| component < 0)
+            .map(|(component, amount)| amount / (-component / steps))
+            .fold(steps, i64::min)
+    }
+
+    // The most steps whose products still fit under the container's caps, capped at the full
+    // rate.
+    fn capacity_steps(&self, container: &Container) -> i64 {
+        let steps = self.steps();
+        let room = |max: Option<i64>, amount: i64, component: i64| match max {
+            Some(max) if component > 0 => (max - amount).max(0) / (component / steps),
+            _ => steps,
+        };
+        let limits = container.limits;
+        room(limits.max_fluid, container.fluid.h2o, self.fluid_delta.h2o)
+            .min(room(
+                limits.max_solid,
+                container.solid.total(),
+                self.solid_delta.total(),
+            ))
+            .min(steps)
+    }
 
-        let carbon = gas.co2 + gas.co + solid.ch2o;
-        let hydrogen = 2 * (gas.h2o + fluid.h2o + solid.ch2o);
-        let oxygen =
-            2 * gas.o2 + 2 * gas.co2 + gas.co + gas.h2o + fluid.h2o + solid.ch2o;
+    fn can_apply(&self, container: &Container) -> bool {
+        container.gas.can_apply_delta(self.gas_delta)
+            && container.fluid.can_apply_delta(self.fluid_delta)
+            && container.solid.can_apply_delta(self.solid_delta)
+    }
+
+    fn apply(&self, container: &mut Container) {
+        container.gas.apply_delta(self.gas_delta);
+        container.fluid.apply_delta(self.fluid_delta);
+        container.solid.apply_delta(self.solid_delta);
+    }
+
+    // What this reaction would do to `container` this tick, if anything.
+    fn planned(&self, container: &Container) -> Option<Reaction> {
+        if self
+            .requirement
+            .is_some_and(|requirement| !requirement.is_met(container))
+        {
+            return None;
+        }
+        let rate_steps = self
+            .rate_steps(container)
+            .min(self.capacity_steps(container));
+        if rate_steps == 0 {
+            return None;
+        }
+        let reaction = self.scaled(rate_steps);
+        match reaction.mode {
+            ReactionMode::AllOrNothing => reaction.can_apply(container).then_some(reaction),
+            ReactionMode::Saturating => {
+                let taken = reaction.available_steps(container);
+                (taken > 0).then(|| reaction.scaled(taken))
+            }
+        }
+    }
 
-        carbon == 0 && hydrogen == 0 && oxygen == 0
+    // Atoms created per run; zero for every reaction the engine accepts.
+    fn residual(&self) -> AtomCount {
+        AtomCount::of(self.gas_delta, self.fluid_delta, self.solid_delta)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EngineStats {
+    pub container_count: usize,
+    pub pipe_count: usize,
+    pub pump_count: usize,
+    pub reaction_count: usize,
+    // Summed over every container, handy for checking conservation across ticks.
+    pub total_gas: Gas,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Engine {
     containers: Vec<Container>,
     pipes: Vec<Pipe>,
+    // Gas moved from each pipe's `a` to its `b` during the last tick, aligned with `pipes`.
+    last_pipe_flows: Vec<Gas>,
+    // Containers that burst during the last tick, in id order.
+    last_bursts: Vec<BurstEvent>,
+    pumps: Vec<Pump>,
+    leaks: Vec<Leak>,
+    freeze_thaws: Vec<FreezeThaw>,
+    alarms: Vec<(ContainerId, Alarm)>,
     reactions: Vec<Reaction>,
+    // Unique names, unlike labels, so scenarios can look containers up by them. Kept apart from
+    // labels so relabeling a container for display never breaks a lookup.
+    names: BTreeMap<String, ContainerId>,
     root: ContainerId,
+    // Per-tick container pressures, recorded only once `enable_history` is called.
+    history: Option<Vec<Vec<i64>>>,
+    reaction_scheduling: ReactionScheduling,
+    pipe_scheduling: PipeScheduling,
+    // Thousandths of the gap between each parent and child closed per tick; zero seals them.
+    diffusion_rate: i64,
 }
 
 impl Engine {
@@ -221,10 +1045,21 @@ impl Engine {
         let mut engine = Self {
             containers: Vec::new(),
             pipes: Vec::new(),
+            last_pipe_flows: Vec::new(),
+            last_bursts: Vec::new(),
+            pumps: Vec::new(),
+            leaks: Vec::new(),
+            freeze_thaws: Vec::new(),
+            alarms: Vec::new(),
             reactions: Vec::new(),
+            names: BTreeMap::new(),
             root: ContainerId(0),
+            history: None,
+            reaction_scheduling: ReactionScheduling::Sequential,
+            pipe_scheduling: PipeScheduling::Sequential,
+            diffusion_rate: 0,
         };
-        let id = engine.insert_container(volume, gas, fluid, solid);
+        let id = engine.insert_container(volume, gas, fluid, solid, ContainerLimits::default());
         engine.root = id;
         engine
     }
@@ -241,13 +1076,152 @@ impl Engine {
         fluid: Fluid,
         solid: Solid,
     ) -> ContainerId {
-        let id = self.insert_container(volume, gas, fluid, solid);
-        self.containers[parent.index()].children.push(id);
-        id
+        let limits = ContainerLimits::default();
+        self.add_container_with_limits(parent, volume, gas, fluid, solid, limits)
     }
 
-    pub fn container(&self, id: ContainerId) -> &Container {
-        &self.containers[id.index()]
+    // Labels are display text for logs and reports, like the engine CLI's listing. They need
+    // not be unique, so `find_by_label` is only a convenience; scenarios that must find one
+    // specific container use names instead.
+    pub fn add_labeled_container(
+        &mut self,
+        parent: ContainerId,
+        label: &str,
+        volume: Volume,
+        gas: Gas,
+        fluid: Fluid,
+        solid: Solid,
+    ) -> ContainerId {
+        let id = self.add_container(parent, volume, gas, fluid, solid);
+        self.set_label(id, label);
+        id
+    }
+
+    // Like `add_labeled_container`, but the name must be unique. Nothing is added when it is
+    // already taken.
+    pub fn add_named_container(
+        &mut self,
+        parent: ContainerId,
+        name: &str,
+        volume: Volume,
+        gas: Gas,
+        fluid: Fluid,
+        solid: Solid,
+    ) -> Result<ContainerId, NameTaken> {
+        self.check_name_free(name, None)?;
+        let id = self.add_container(parent, volume, gas, fluid, solid);
+        self.set_name(id, name)?;
+        Ok(id)
+    }
+
+    pub fn add_container_with_limits(
+        &mut self,
+        parent: ContainerId,
+        volume: Volume,
+        gas: Gas,
+        fluid: Fluid,
+        solid: Solid,
+        limits: ContainerLimits,
+    ) -> ContainerId {
+        self.assert_container_exists(parent, "parent");
+        assert!(
+            limits
+                .max_fluid
+                .is_none_or(|max_fluid| fluid.h2o <= max_fluid),
+            "initial fluid exceeds max_fluid"
+        );
+        assert!(
+            limits
+                .max_solid
+                .is_none_or(|max_solid| solid.total() <= max_solid),
+            "initial solid exceeds max_solid"
+        );
+        let id = self.insert_container(volume, gas, fluid, solid, limits);
+        self.containers[parent.index()].children.push(id);
+        id
+    }
+
+    pub fn set_label(&mut self, id: ContainerId, label: &str) {
+        self.assert_container_exists(id, "labeled");
+        self.containers[id.index()].label = Some(label.to_owned());
+    }
+
+    // Names the container and labels it with the name too, so named containers show up by
+    // name in logs without a second call. A later `set_label` changes only the display text,
+    // not the name. A container has at most one name, so this replaces any earlier one. Fails,
+    // changing nothing, if another container has it.
+    pub fn set_name(&mut self, id: ContainerId, name: &str) -> Result<(), NameTaken> {
+        self.assert_container_exists(id, "named");
+        self.check_name_free(name, Some(id))?;
+        self.names.retain(|_, named| *named != id);
+        self.names.insert(name.to_owned(), id);
+        self.set_label(id, name);
+        Ok(())
+    }
+
+    pub fn container_by_name(&self, name: &str) -> Option<ContainerId> {
+        self.names.get(name).copied()
+    }
+
+    fn check_name_free(&self, name: &str, owner: Option<ContainerId>) -> Result<(), NameTaken> {
+        match self.container_by_name(name) {
+            Some(container) if Some(container) != owner => Err(NameTaken {
+                name: name.to_owned(),
+                container,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    // Light reaching the container in thousandths of full sunlight; containers start fully lit.
+    // Only `ReactionRate::Lit` reactions read it, so callers update it as the sun moves.
+    pub fn set_light_level(&mut self, id: ContainerId, light_level: i64) {
+        self.assert_container_exists(id, "lit");
+        assert!(
+            (0..=FULL_LIGHT).contains(&light_level),
+            "light level must be between 0 and FULL_LIGHT"
+        );
+        self.containers[id.index()].light_level = light_level;
+    }
+
+    // Lights the container like flat ground under a sun at `angles`, so `ReactionRate::Lit`
+    // reactions follow the day; dark once the sun has set.
+    pub fn set_sunlight(&mut self, id: ContainerId, angles: SolarAngles) {
+        let light_level = (irradiance_fraction(angles) * FULL_LIGHT as f32).round() as i64;
+        self.set_light_level(id, light_level.clamp(0, FULL_LIGHT));
+    }
+
+    pub fn set_temperature(&mut self, id: ContainerId, temperature: i64) {
+        self.assert_container_exists(id, "temperature");
+        self.containers[id.index()].temperature = temperature;
+    }
+
+    // The root vents into itself, so a limit on it is never enforced.
+    pub fn set_max_pressure(&mut self, id: ContainerId, max_pressure: Option<i64>) {
+        self.assert_container_exists(id, "pressure-limited");
+        assert!(
+            max_pressure.is_none_or(|max| max >= 0),
+            "max pressure must be non-negative"
+        );
+        self.containers[id.index()].max_pressure = max_pressure;
+    }
+
+    // Returns the first container with this label, in creation order. Labels may repeat, so
+    // prefer `container_by_name` when the container must be unique.
+    pub fn find_by_label(&self, label: &str) -> Option<ContainerId> {
+        self.containers
+            .iter()
+            .position(|container| container.label() == Some(label))
+            .map(ContainerId)
+    }
+
+    // All containers in creation order, starting with the root.
+    pub fn container_ids(&self) -> impl Iterator<Item = ContainerId> {
+        (0..self.containers.len()).map(ContainerId)
+    }
+
+    pub fn container(&self, id: ContainerId) -> &Container {
+        &self.containers[id.index()]
     }
 
     pub fn container_mut(&mut self, id: ContainerId) -> &mut Container {
@@ -258,198 +1232,3372 @@ impl Engine {
         &self.pipes
     }
 
-    pub fn add_pipe(&mut self, a: ContainerId, b: ContainerId, flow_rate: Gas) {
-        assert!(a != b, "pipe endpoints must be different");
-        self.pipes.push(Pipe::new(a, b, flow_rate));
+    pub fn pipe(&self, id: PipeId) -> &Pipe {
+        &self.pipes[id.index()]
     }
 
-    pub fn add_reaction(
-        &mut self,
-        container: ContainerId,
-        gas_delta: Gas,
-        fluid_delta: Fluid,
-        solid_delta: Solid,
-    ) {
-        // Use negative values to consume resources.
-        let reaction = Reaction::new(container, gas_delta, fluid_delta, solid_delta);
-        assert!(reaction.check(), "reaction is not atom-balanced");
-        self.reactions.push(reaction);
+    // Opens or closes the pipe's valve; a closed pipe moves nothing until it is reopened.
+    pub fn set_pipe_open(&mut self, id: PipeId, open: bool) {
+        assert!(
+            id.index() < self.pipes.len(),
+            "pipe {} does not exist (engine has {} pipes)",
+            id.index(),
+            self.pipes.len()
+        );
+        self.pipes[id.index()].open = open;
     }
 
-    pub fn tick(&mut self) {
-        for reaction in self.reactions.iter().copied() {
-            let container = &mut self.containers[reaction.container.index()];
-            if !container.gas.can_apply_delta(reaction.gas_delta)
-                || !container.fluid.can_apply_delta(reaction.fluid_delta)
-                || !container.solid.can_apply_delta(reaction.solid_delta)
-            {
-                continue;
+    // Gas each pipe actually moved during the last tick, after clamping to what was available,
+    // aligned with `pipes()`. Positive amounts flowed from `a` to `b`. Pipes added since then
+    // read as zero until the next tick.
+    pub fn last_pipe_flows(&self) -> &[Gas] {
+        &self.last_pipe_flows
+    }
+
+    pub fn last_bursts(&self) -> &[BurstEvent] {
+        &self.last_bursts
+    }
+
+    // Pipes connected to `id`, in insertion order, with the end `id` is attached to.
+    pub fn pipes_for(&self, id: ContainerId) -> impl Iterator<Item = (PipeEnd, &Pipe)> {
+        self.pipes.iter().filter_map(move |pipe| {
+            if pipe.a == id {
+                Some((PipeEnd::A, pipe))
+            } else if pipe.b == id {
+                Some((PipeEnd::B, pipe))
+            } else {
+                None
             }
-            container.gas.apply_delta(reaction.gas_delta);
-            container.fluid.apply_delta(reaction.fluid_delta);
-            container.solid.apply_delta(reaction.solid_delta);
-        }
+        })
+    }
 
-        for pipe in self.pipes.clone() {
-            self.apply_pipe_flow(pipe);
-        }
+    // See `Pipe::flow_rate` for how `tick` and `tick_implicit` read the rate.
+    pub fn add_pipe(&mut self, a: ContainerId, b: ContainerId, flow_rate: Gas) -> PipeId {
+        self.add_asymmetric_pipe(a, b, flow_rate, flow_rate)
     }
 
-    fn insert_container(
+    pub fn add_asymmetric_pipe(
         &mut self,
-        volume: Volume,
-        gas: Gas,
-        fluid: Fluid,
-        solid: Solid,
-    ) -> ContainerId {
-        let id = ContainerId(self.containers.len());
-        self.containers
-            .push(Container::new(volume, gas, fluid, solid));
-        id
+        a: ContainerId,
+        b: ContainerId,
+        flow_rate: Gas,
+        reverse_flow_rate: Gas,
+    ) -> PipeId {
+        self.assert_container_exists(a, "pipe endpoint");
+        self.assert_container_exists(b, "pipe endpoint");
+        assert!(a != b, "pipe endpoints must be different");
+        self.pipes
+            .push(Pipe::asymmetric(a, b, flow_rate, reverse_flow_rate));
+        PipeId(self.pipes.len() - 1)
     }
 
-    fn apply_pipe_flow(&mut self, pipe: Pipe) {
-        let (a, b) = self.container_pair_mut(pipe.a, pipe.b);
-        let mut delta = Gas::zero();
-        let mut inverse = Gas::zero();
+    pub fn pumps(&self) -> &[Pump] {
+        &self.pumps
+    }
 
-        let o2_flow = Self::flow_amount(
-            a.gas.o2,
-            a.volume,
-            b.gas.o2,
-            b.volume,
-            pipe.flow_rate.o2,
+    pub fn add_pump(&mut self, from: ContainerId, to: ContainerId, rate: Gas) {
+        self.assert_container_exists(from, "pump source");
+        self.assert_container_exists(to, "pump target");
+        assert!(from != to, "pump endpoints must be different");
+        self.pumps.push(Pump::new(from, to, rate));
+    }
+
+    pub fn leaks(&self) -> &[Leak] {
+        &self.leaks
+    }
+
+    pub fn add_leak(&mut self, container: ContainerId, rate: Gas) {
+        self.assert_container_exists(container, "leak");
+        assert!(rate.is_non_negative(), "leak rates must be non-negative");
+        self.leaks.push(Leak { container, rate });
+    }
+
+    pub fn freeze_thaws(&self) -> &[FreezeThaw] {
+        &self.freeze_thaws
+    }
+
+    pub fn add_freeze_thaw(&mut self, container: ContainerId, freeze_temperature: i64) {
+        self.assert_container_exists(container, "freeze/thaw");
+        self.freeze_thaws.push(FreezeThaw {
+            container,
+            freeze_temperature,
+        });
+    }
+
+    pub fn alarms(&self) -> &[(ContainerId, Alarm)] {
+        &self.alarms
+    }
+
+    pub fn add_alarm(&mut self, container: ContainerId, alarm: Alarm) {
+        self.assert_container_exists(container, "alarm");
+        self.alarms.push((container, alarm));
+    }
+
+    // Alarms only read the current state, so checking them never changes the simulation.
+    pub fn check_alarms(&self) -> Vec<TrippedAlarm> {
+        let mut tripped = Vec::new();
+        for (index, &(container, alarm)) in self.alarms.iter().enumerate() {
+            let container_state = self.container(container);
+            let volume = container_state.volume();
+            let gas = container_state.gas();
+            let checks = [
+                (
+                    AlarmCondition::LowO2,
+                    alarm
+                        .o2_min
+                        .is_some_and(|min| Gas::partial_pressure(gas.o2, volume) < min),
+                ),
+                (
+                    AlarmCondition::HighCo2,
+                    alarm
+                        .co2_max
+                        .is_some_and(|max| Gas::partial_pressure(gas.co2, volume) > max),
+                ),
+                (
+                    AlarmCondition::LowPressure,
+                    alarm
+                        .pressure_min
+                        .is_some_and(|min| container_state.pressure() < min),
+                ),
+            ];
+            for (condition, failed) in checks {
+                if failed {
+                    tripped.push(TrippedAlarm {
+                        alarm: index,
+                        container,
+                        condition,
+                    });
+                }
+            }
+        }
+        tripped
+    }
+
+    // Folds `b` into `a`: volumes, contents and capacity caps add up, temperatures average by
+    // volume, `b`'s children move to `a`, and everything attached to `b` is redirected to `a`.
+    // Pipes and pumps left joining `a` to itself are dropped. `b`'s slot is removed, so higher
+    // ids shift down by one like `Vec::remove`; the returned id is `a`'s new id.
+    pub fn merge_containers(&mut self, a: ContainerId, b: ContainerId) -> ContainerId {
+        self.assert_container_exists(a, "merge target");
+        self.assert_container_exists(b, "merged");
+        assert!(a != b, "cannot merge a container with itself");
+        assert!(b != self.root, "the root cannot be merged away");
+        assert!(
+            !self.is_ancestor(b, a),
+            "cannot merge a container into one of its descendants"
         );
-        let co2_flow = Self::flow_amount(
-            a.gas.co2,
-            a.volume,
-            b.gas.co2,
-            b.volume,
-            pipe.flow_rate.co2,
+
+        let merged = self.containers[b.index()].clone();
+        for container in &mut self.containers {
+            container.children.retain(|&child| child != b);
+        }
+        let target = &mut self.containers[a.index()];
+        let (volume_a, volume_b) = (target.volume.value(), merged.volume.value());
+        let heat = target.temperature as i128 * volume_a as i128
+            + merged.temperature as i128 * volume_b as i128;
+        target.temperature = (heat / (volume_a + volume_b) as i128) as i64;
+        target.volume = Volume::new(volume_a + volume_b);
+        target.gas.apply_delta(merged.gas);
+        target.fluid.h2o += merged.fluid.h2o;
+        target.solid.ch2o += merged.solid.ch2o;
+        target.solid.ice += merged.solid.ice;
+        // An uncapped half leaves the merged container uncapped.
+        let add_caps = |x: Option<i64>, y: Option<i64>| x.zip(y).map(|(x, y)| x + y);
+        target.limits = ContainerLimits {
+            max_fluid: add_caps(target.limits.max_fluid, merged.limits.max_fluid),
+            max_solid: add_caps(target.limits.max_solid, merged.limits.max_solid),
+        };
+        if target.label.is_none() {
+            target.label = merged.label;
+        }
+        // The weaker wall bursts first.
+        target.max_pressure = match (target.max_pressure, merged.max_pressure) {
+            (Some(x), Some(y)) => Some(x.min(y)),
+            (x, y) => x.or(y),
+        };
+        target.children.extend(merged.children);
+        // Like the label, `b`'s name carries over only if `a` has none.
+        if self.names.values().any(|&named| named == a) {
+            self.names.retain(|_, named| *named != b);
+        }
+
+        let redirect = |id: ContainerId| if id == b { a } else { id };
+        let mut flows = std::mem::take(&mut self.last_pipe_flows).into_iter();
+        let pipes = std::mem::take(&mut self.pipes);
+        for pipe in pipes {
+            let flow = flows.next();
+            let (pipe_a, pipe_b) = (redirect(pipe.a), redirect(pipe.b));
+            if pipe_a != pipe_b {
+                self.pipes.push(Pipe {
+                    a: pipe_a,
+                    b: pipe_b,
+                    ..pipe
+                });
+                self.last_pipe_flows.extend(flow);
+            }
+        }
+        self.pumps.retain_mut(|pump| {
+            pump.from = redirect(pump.from);
+            pump.to = redirect(pump.to);
+            pump.from != pump.to
+        });
+        self.remove_container_slot(b, redirect);
+        if a.index() > b.index() {
+            ContainerId(a.index() - 1)
+        } else {
+            a
+        }
+    }
+
+    // Moves `num / den` of `id`'s volume, contents and capacity caps into a new sibling,
+    // rounding the new part down so totals are conserved exactly. Children, pipes, reactions
+    // and the label stay with `id`; light level and temperature are copied.
+    pub fn split_container(&mut self, id: ContainerId, num: i64, den: i64) -> ContainerId {
+        self.assert_container_exists(id, "split");
+        assert!(
+            0 < num && num < den,
+            "split fraction must be between 0 and 1"
         );
-        let co_flow = Self::flow_amount(
-            a.gas.co,
-            a.volume,
-            b.gas.co,
-            b.volume,
-            pipe.flow_rate.co,
+        let parent = self
+            .container_ids()
+            .find(|&parent| self.containers[parent.index()].children.contains(&id))
+            .expect("the root has no parent to hold the split-off part");
+
+        let source = &mut self.containers[id.index()];
+        let part = |amount: i64| (amount as i128 * num as i128 / den as i128) as i64;
+        let volume = part(source.volume.value());
+        assert!(
+            volume > 0 && volume < source.volume.value(),
+            "both parts of a split need a positive volume"
         );
-        let h2o_flow = Self::flow_amount(
-            a.gas.h2o,
-            a.volume,
-            b.gas.h2o,
-            b.volume,
-            pipe.flow_rate.h2o,
+        let gas = Gas {
+            o2: part(source.gas.o2),
+            co2: part(source.gas.co2),
+            co: part(source.gas.co),
+            h2o: part(source.gas.h2o),
+        };
+        let fluid = Fluid {
+            h2o: part(source.fluid.h2o),
+        };
+        let solid = Solid {
+            ch2o: part(source.solid.ch2o),
+            ice: part(source.solid.ice),
+        };
+        let limits = ContainerLimits {
+            max_fluid: source.limits.max_fluid.map(part),
+            max_solid: source.limits.max_solid.map(part),
+        };
+        source.volume = Volume::new(source.volume.value() - volume);
+        source.gas.apply_delta(gas * -1);
+        source.fluid.h2o -= fluid.h2o;
+        source.solid.ch2o -= solid.ch2o;
+        source.solid.ice -= solid.ice;
+        source.limits.max_fluid = source
+            .limits
+            .max_fluid
+            .zip(limits.max_fluid)
+            .map(|(x, y)| x - y);
+        source.limits.max_solid = source
+            .limits
+            .max_solid
+            .zip(limits.max_solid)
+            .map(|(x, y)| x - y);
+        let (light_level, temperature) = (source.light_level, source.temperature);
+        let max_pressure = source.max_pressure;
+
+        let split = self.insert_container(Volume::new(volume), gas, fluid, solid, limits);
+        let container = &mut self.containers[split.index()];
+        container.light_level = light_level;
+        container.temperature = temperature;
+        container.max_pressure = max_pressure;
+        self.containers[parent.index()].children.push(split);
+        split
+    }
+
+    // Moves up to `amount` of each species at once, unlike pipes which are rate-limited per tick.
+    pub fn transfer_gas(&mut self, from: ContainerId, to: ContainerId, amount: Gas) -> Gas {
+        assert!(
+            amount.is_non_negative(),
+            "transfer amount must be non-negative"
         );
+        let (source, target) = self.container_pair_mut(from, to);
+        let moved = Gas {
+            o2: amount.o2.min(source.gas.o2),
+            co2: amount.co2.min(source.gas.co2),
+            co: amount.co.min(source.gas.co),
+            h2o: amount.h2o.min(source.gas.h2o),
+        };
+        source.gas.apply_delta(Gas {
+            o2: -moved.o2,
+            co2: -moved.co2,
+            co: -moved.co,
+            h2o: -moved.h2o,
+        });
+        target.gas.apply_delta(moved);
+        moved
+    }
 
-        delta.o2 = -o2_flow;
-        delta.co2 = -co2_flow;
-        delta.co = -co_flow;
-        delta.h2o = -h2o_flow;
+    // Reactions in `container`, in the order they run.
+    pub fn reactions_for(&self, container: ContainerId) -> Vec<ReactionInfo> {
+        self.assert_container_exists(container, "reaction");
+        self.reactions
+            .iter()
+            .filter(|reaction| reaction.container == container)
+            .map(|reaction| ReactionInfo {
+                gas_delta: reaction.gas_delta,
+                fluid_delta: reaction.fluid_delta,
+                solid_delta: reaction.solid_delta,
+                mode: reaction.mode,
+                rate: reaction.rate,
+                requirement: reaction.requirement,
+            })
+            .collect()
+    }
 
-        inverse.o2 = o2_flow;
-        inverse.co2 = co2_flow;
-        inverse.co = co_flow;
-        inverse.h2o = h2o_flow;
+    pub fn add_reaction(
+        &mut self,
+        container: ContainerId,
+        gas_delta: Gas,
+        fluid_delta: Fluid,
+        solid_delta: Solid,
+    ) {
+        self.add_reaction_with_mode(
+            container,
+            gas_delta,
+            fluid_delta,
+            solid_delta,
+            ReactionMode::AllOrNothing,
+        );
+    }
 
-        a.gas.apply_delta(delta);
-        b.gas.apply_delta(inverse);
+    pub fn add_reaction_with_mode(
+        &mut self,
+        container: ContainerId,
+        gas_delta: Gas,
+        fluid_delta: Fluid,
+        solid_delta: Solid,
+        mode: ReactionMode,
+    ) {
+        self.add_reaction_with_rate(
+            container,
+            gas_delta,
+            fluid_delta,
+            solid_delta,
+            mode,
+            ReactionRate::Constant,
+        );
     }
 
-    fn container_pair_mut(
+    // Rates other than `Constant` make the reaction state-dependent: how much it does in a
+    // tick depends on what the container holds when the reaction runs.
+    pub fn add_reaction_with_rate(
         &mut self,
-        a: ContainerId,
-        b: ContainerId,
-    ) -> (&mut Container, &mut Container) {
-        let a_idx = a.index();
-        let b_idx = b.index();
-        assert!(a_idx != b_idx, "container pair must be different");
-        if a_idx < b_idx {
-            let (left, right) = self.containers.split_at_mut(b_idx);
-            (&mut left[a_idx], &mut right[0])
-        } else {
-            let (left, right) = self.containers.split_at_mut(a_idx);
-            (&mut right[0], &mut left[b_idx])
+        container: ContainerId,
+        gas_delta: Gas,
+        fluid_delta: Fluid,
+        solid_delta: Solid,
+        mode: ReactionMode,
+        rate: ReactionRate,
+    ) {
+        self.assert_container_exists(container, "reaction");
+        let added = self.try_add_reaction_with_rate(
+            container,
+            gas_delta,
+            fluid_delta,
+            solid_delta,
+            mode,
+            rate,
+        );
+        if let Err(error) = added {
+            panic!("{error}");
         }
     }
 
-    fn flow_amount(
-        amount_a: i64,
-        volume_a: Volume,
-        amount_b: i64,
-        volume_b: Volume,
-        max_flow: i64,
-    ) -> i64 {
-        let va = volume_a.value() as i128;
-        let vb = volume_b.value() as i128;
-        let numerator = (amount_a as i128) * vb - (amount_b as i128) * va;
-        let denom = va + vb;
-        if denom <= 0 {
-            return 0;
+    // Non-panicking `add_reaction` for untrusted input such as config files.
+    pub fn try_add_reaction(
+        &mut self,
+        container: ContainerId,
+        gas_delta: Gas,
+        fluid_delta: Fluid,
+        solid_delta: Solid,
+    ) -> Result<ReactionId, ReactionError> {
+        self.try_add_reaction_with_rate(
+            container,
+            gas_delta,
+            fluid_delta,
+            solid_delta,
+            ReactionMode::AllOrNothing,
+            ReactionRate::Constant,
+        )
+    }
+
+    pub fn try_add_reaction_with_rate(
+        &mut self,
+        container: ContainerId,
+        gas_delta: Gas,
+        fluid_delta: Fluid,
+        solid_delta: Solid,
+        mode: ReactionMode,
+        rate: ReactionRate,
+    ) -> Result<ReactionId, ReactionError> {
+        if container.index() >= self.containers.len() {
+            return Err(ReactionError::UnknownContainer(container));
         }
-        let mut equalize = numerator / denom;
-        let max_flow = max_flow as i128;
-        let hi = (amount_a as i128).min(max_flow);
-        let lo = -(amount_b as i128).min(max_flow);
-        if equalize > hi {
-            equalize = hi;
-        } else if equalize < lo {
-            equalize = lo;
+        if let ReactionRate::InhibitedBy {
+            half_rate_pressure, ..
         }
-        equalize as i64
+        | ReactionRate::Lit {
+            half_rate_pressure, ..
+        } = rate
+        {
+            if half_rate_pressure <= 0 {
+                return Err(ReactionError::NonPositiveHalfRatePressure(
+                    half_rate_pressure,
+                ));
+            }
+        }
+        // Use negative values to consume resources.
+        let reaction = Reaction::new(container, gas_delta, fluid_delta, solid_delta, mode, rate);
+        self.try_push_reaction(reaction)
     }
-}
 
-pub fn add_human(engine: &mut Engine, container: ContainerId, o2_per_tick: i64) {
-    assert!(o2_per_tick >= 0, "o2_per_tick must be non-negative");
-    engine.add_reaction(
-        container,
+    // An all-or-nothing reaction that only runs while `requirement` holds in its container.
+    pub fn add_reaction_with_requirement(
+        &mut self,
+        container: ContainerId,
+        gas_delta: Gas,
+        fluid_delta: Fluid,
+        solid_delta: Solid,
+        requirement: ReactionRequirement,
+    ) -> ReactionId {
+        self.assert_container_exists(container, "reaction");
+        let reaction = Reaction {
+            requirement: Some(requirement),
+            ..Reaction::new(
+                container,
+                gas_delta,
+                fluid_delta,
+                solid_delta,
+                ReactionMode::AllOrNothing,
+                ReactionRate::Constant,
+            )
+        };
+        match self.try_push_reaction(reaction) {
+            Ok(id) => id,
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    fn try_push_reaction(&mut self, reaction: Reaction) -> Result<ReactionId, ReactionError> {
+        if let Some(ReactionRequirement::AtLeast(gas)) = reaction.requirement {
+            if !gas.is_non_negative() {
+                return Err(ReactionError::NegativeRequirement(gas));
+            }
+        }
+        let residual = reaction.residual();
+        if residual != AtomCount::zero() {
+            return Err(ReactionError::Unbalanced {
+                carbon: residual.carbon,
+                hydrogen: residual.hydrogen,
+                oxygen: residual.oxygen,
+            });
+        }
+        self.reactions.push(reaction);
+        Ok(ReactionId(self.reactions.len() - 1))
+    }
+
+    pub fn set_reaction_scheduling(&mut self, scheduling: ReactionScheduling) {
+        self.reaction_scheduling = scheduling;
+    }
+
+    pub fn set_pipe_scheduling(&mut self, scheduling: PipeScheduling) {
+        self.pipe_scheduling = scheduling;
+    }
+
+    // Models imperfect seals: every tick each gas species moves this many thousandths of the
+    // way to equal partial pressures between every container and its parent.
+    pub fn set_diffusion_rate(&mut self, rate: i64) {
+        assert!(
+            (0..=FULL_DIFFUSION).contains(&rate),
+            "diffusion rate must be between 0 and FULL_DIFFUSION"
+        );
+        self.diffusion_rate = rate;
+    }
+
+    // Ticks are deterministic so replays and lockstep peers stay in sync: reactions run per
+    // `ReactionScheduling`, then phase changes and pumps in insertion order, pipes per
+    // `PipeScheduling`, diffusion down the container tree, leaks in insertion order and bursts
+    // in id order, using integer math only.
+    pub fn tick(&mut self) {
+        self.apply_reactions();
+        self.finish_explicit_tick();
+    }
+
+    // Like `tick`, but each container's reactions run on the rayon thread pool. Reactions only
+    // touch their own container and keep their order within it, so the result is identical to
+    // `tick`; pipes, pumps and everything else spanning containers still run serially.
+    pub fn tick_parallel(&mut self) {
+        let scheduling = self.reaction_scheduling;
+        let groups = self.reactions_by_container();
+        self.containers
+            .par_iter_mut()
+            .zip(groups.par_iter())
+            .filter(|(_, reactions)| !reactions.is_empty())
+            .for_each(|(container, reactions)| react(container, reactions, scheduling));
+        self.finish_explicit_tick();
+    }
+
+    fn finish_explicit_tick(&mut self) {
+        self.apply_phase_changes();
+        self.apply_pumps();
+
+        self.last_pipe_flows = match self.pipe_scheduling {
+            PipeScheduling::Sequential => {
+                let pipes = self.pipes.clone();
+                pipes
+                    .into_iter()
+                    .map(|pipe| self.apply_pipe_flow(pipe))
+                    .collect()
+            }
+            PipeScheduling::Simultaneous => self.apply_pipe_flows_simultaneously(),
+        };
+
+        self.apply_diffusion();
+        self.apply_leaks();
+        self.apply_bursts();
+        self.record_history();
+    }
+
+    // Returns the gas each pipe moved from `a` to `b`, aligned with `pipes`.
+    fn apply_pipe_flows_simultaneously(&mut self) -> Vec<Gas> {
+        let mut flows: Vec<Gas> = self
+            .pipes
+            .iter()
+            .map(|pipe| {
+                let mut flow = Gas::zero();
+                if pipe.open {
+                    let a = &self.containers[pipe.a.index()];
+                    let b = &self.containers[pipe.b.index()];
+                    for species in Species::ALL {
+                        *flow.amount_mut(species) = Self::flow_amount(
+                            a.gas.amount(species),
+                            a.volume,
+                            b.gas.amount(species),
+                            b.volume,
+                            pipe.flow_rate.amount(species),
+                            pipe.reverse_flow_rate.amount(species),
+                        );
+                    }
+                }
+                flow
+            })
+            .collect();
+
+        // Each pipe is capped at its source's amount on its own, so only the sum can overdraw.
+        let mut outflow = vec![Gas::zero(); self.containers.len()];
+        for (pipe, flow) in self.pipes.iter().zip(&flows) {
+            for species in Species::ALL {
+                let amount = flow.amount(species);
+                let source = if amount > 0 { pipe.a } else { pipe.b };
+                *outflow[source.index()].amount_mut(species) += amount.abs();
+            }
+        }
+        for (pipe, flow) in self.pipes.iter().zip(&mut flows) {
+            for species in Species::ALL {
+                let amount = flow.amount_mut(species);
+                let source = if *amount > 0 { pipe.a } else { pipe.b };
+                let available = self.containers[source.index()].gas.amount(species);
+                let total = outflow[source.index()].amount(species);
+                if total > available {
+                    *amount = (*amount as i128 * available as i128 / total as i128) as i64;
+                }
+            }
+        }
+
+        for (pipe, flow) in self.pipes.iter().zip(&flows) {
+            self.containers[pipe.a.index()].gas.apply_delta(*flow * -1);
+            self.containers[pipe.b.index()].gas.apply_delta(*flow);
+        }
+        flows
+    }
+
+    // Like `tick`, but all pipes are solved together as one implicit (backward Euler) step per
+    // species, so the result doesn't depend on pipe order and loops settle without overshoot.
+    // Here each pipe's flow rate acts as a conductance: moles per tick per unit of partial
+    // pressure difference. The network is solved in floating point, so unlike `tick` this is
+    // only reproducible on the same platform. The solution is rounded back to whole moles
+    // without creating or destroying any.
+    pub fn tick_implicit(&mut self) {
+        self.apply_reactions();
+        self.apply_phase_changes();
+        self.apply_pumps();
+
+        self.last_pipe_flows = vec![Gas::zero(); self.pipes.len()];
+        for species in Species::ALL {
+            self.solve_pipe_network(species);
+        }
+
+        self.apply_diffusion();
+        self.apply_leaks();
+        self.apply_bursts();
+        self.record_history();
+    }
+
+    // Starts recording every container's pressure after each tick, indexed by container.
+    pub fn enable_history(&mut self) {
+        self.history.get_or_insert_with(Vec::new);
+    }
+
+    pub fn history(&self) -> &[Vec<i64>] {
+        self.history.as_deref().unwrap_or_default()
+    }
+
+    pub fn clear_history(&mut self) {
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+    }
+
+    pub fn stats(&self) -> EngineStats {
+        let mut total_gas = Gas::zero();
+        for container in &self.containers {
+            total_gas.apply_delta(container.gas);
+        }
+        EngineStats {
+            container_count: self.containers.len(),
+            pipe_count: self.pipes.len(),
+            pump_count: self.pumps.len(),
+            reaction_count: self.reactions.len(),
+            total_gas,
+        }
+    }
+
+    // Every reaction is atom-balanced and pipes, pumps and transfers only move gas, so ticking
+    // leaves this unchanged except where atoms really leave the engine: leaks and direct edits
+    // through `container_mut`.
+    pub fn atom_inventory(&self) -> AtomCount {
+        let mut inventory = AtomCount::zero();
+        for container in &self.containers {
+            inventory.add(AtomCount::of(
+                container.gas,
+                container.fluid,
+                container.solid,
+            ));
+        }
+        inventory
+    }
+
+    // Compares against an earlier `atom_inventory`, returning how far each element drifted.
+    pub fn check_conserved(&self, baseline: &AtomCount) -> Result<(), AtomCount> {
+        let inventory = self.atom_inventory();
+        if inventory == *baseline {
+            return Ok(());
+        }
+        Err(AtomCount {
+            carbon: inventory.carbon - baseline.carbon,
+            hydrogen: inventory.hydrogen - baseline.hydrogen,
+            oxygen: inventory.oxygen - baseline.oxygen,
+        })
+    }
+
+    // For scenario tests; see `atom_inventory` for what legitimately breaks conservation.
+    pub fn assert_conserved(&self, baseline: &AtomCount) {
+        if let Err(drift) = self.check_conserved(baseline) {
+            panic!("atoms not conserved: drifted by {drift:?} from {baseline:?}");
+        }
+    }
+
+    // Pressure two containers settle at once an equalizing pipe has moved gas between them:
+    // total moles over total volume, which is where `flow_amount`'s equalize term converges.
+    // Floored like `Gas::pressure_exact`. A container paired with itself is already settled.
+    // Sums in i128 so an atmosphere-sized volume next to a tiny tank can't overflow.
+    pub fn predict_equilibrium(&self, a: ContainerId, b: ContainerId) -> i64 {
+        if a == b {
+            let container = self.container(a);
+            return container.gas.pressure_exact(container.volume);
+        }
+        let moles = |id: ContainerId| self.container(id).gas.total() as i128;
+        let volume = |id: ContainerId| self.container(id).volume.value() as i128;
+        ((moles(a) + moles(b)) / (volume(a) + volume(b))) as i64
+    }
+
+    // Projects by ticking a copy of the engine, so reactions and pipes are all accounted for.
+    // Each projected tick costs a full tick, so the projection gives up after `max_ticks`.
+    // Returns `None` once a tick passes without the amount decreasing (net inflow or a stall),
+    // or when the container is still not empty after `max_ticks`.
+    pub fn time_to_empty(
+        &self,
+        id: ContainerId,
+        species: Species,
+        max_ticks: usize,
+    ) -> Option<usize> {
+        let mut engine = self.clone();
+        let mut amount = engine.container(id).gas.amount(species);
+        let mut ticks = 0;
+        while amount > 0 {
+            if ticks == max_ticks {
+                return None;
+            }
+            engine.tick();
+            ticks += 1;
+            let remaining = engine.container(id).gas.amount(species);
+            if remaining >= amount {
+                return None;
+            }
+            amount = remaining;
+        }
+        Some(ticks)
+    }
+
+    fn is_ancestor(&self, ancestor: ContainerId, id: ContainerId) -> bool {
+        self.containers[ancestor.index()]
+            .children
+            .iter()
+            .any(|&child| child == id || self.is_ancestor(child, id))
+    }
+
+    // Drops the container at `removed` after `redirect` has moved its references elsewhere,
+    // renumbering every id above it.
+    fn remove_container_slot(
+        &mut self,
+        removed: ContainerId,
+        redirect: impl Fn(ContainerId) -> ContainerId,
+    ) {
+        let renumber = |id: ContainerId| {
+            let id = redirect(id);
+            assert!(id != removed, "removed container is still referenced");
+            if id.index() > removed.index() {
+                ContainerId(id.index() - 1)
+            } else {
+                id
+            }
+        };
+        self.containers.remove(removed.index());
+        for container in &mut self.containers {
+            for child in &mut container.children {
+                *child = renumber(*child);
+            }
+        }
+        for pipe in &mut self.pipes {
+            (pipe.a, pipe.b) = (renumber(pipe.a), renumber(pipe.b));
+        }
+        for pump in &mut self.pumps {
+            (pump.from, pump.to) = (renumber(pump.from), renumber(pump.to));
+        }
+        for leak in &mut self.leaks {
+            leak.container = renumber(leak.container);
+        }
+        for freeze_thaw in &mut self.freeze_thaws {
+            freeze_thaw.container = renumber(freeze_thaw.container);
+        }
+        for (container, _) in &mut self.alarms {
+            *container = renumber(*container);
+        }
+        for reaction in &mut self.reactions {
+            reaction.container = renumber(reaction.container);
+        }
+        for burst in &mut self.last_bursts {
+            burst.container = renumber(burst.container);
+        }
+        for named in self.names.values_mut() {
+            *named = renumber(*named);
+        }
+        self.root = renumber(self.root);
+        if let Some(history) = &mut self.history {
+            for pressures in history.iter_mut().filter(|row| row.len() > removed.index()) {
+                pressures.remove(removed.index());
+            }
+        }
+    }
+
+    fn assert_container_exists(&self, id: ContainerId, role: &str) {
+        assert!(
+            id.index() < self.containers.len(),
+            "{role} container {} does not exist (engine has {} containers)",
+            id.index(),
+            self.containers.len()
+        );
+    }
+
+    fn apply_reactions(&mut self) {
+        match self.reaction_scheduling {
+            ReactionScheduling::Sequential => {
+                for reaction in self.reactions.iter().copied() {
+                    let container = &mut self.containers[reaction.container.index()];
+                    if let Some(planned) = reaction.planned(container) {
+                        planned.apply(container);
+                    }
+                }
+            }
+            ReactionScheduling::Simultaneous => self.apply_reactions_simultaneously(),
+        }
+    }
+
+    fn apply_reactions_simultaneously(&mut self) {
+        let groups = self.reactions_by_container();
+        for (container, reactions) in self.containers.iter_mut().zip(&groups) {
+            react_simultaneously(container, reactions);
+        }
+    }
+
+    // Every reaction, grouped by container index and kept in insertion order.
+    fn reactions_by_container(&self) -> Vec<Vec<Reaction>> {
+        let mut groups = vec![Vec::new(); self.containers.len()];
+        for reaction in &self.reactions {
+            groups[reaction.container.index()].push(*reaction);
+        }
+        groups
+    }
+
+    fn record_history(&mut self) {
+        if let Some(history) = &mut self.history {
+            history.push(self.containers.iter().map(Container::pressure).collect());
+        }
+    }
+
+    fn apply_pumps(&mut self) {
+        for pump in self.pumps.clone() {
+            self.transfer_gas(pump.from, pump.to, pump.rate);
+        }
+    }
+
+    // Melting stops at `max_fluid` so capped tanks keep the rest as ice instead of losing it.
+    fn apply_phase_changes(&mut self) {
+        for freeze_thaw in &self.freeze_thaws {
+            let container = &mut self.containers[freeze_thaw.container.index()];
+            let moved = if container.temperature < freeze_thaw.freeze_temperature {
+                let room = container
+                    .limits
+                    .max_solid
+                    .map_or(i64::MAX, |max_solid| max_solid - container.solid.total());
+                container.fluid.h2o.min(room.max(0))
+            } else {
+                let room = container
+                    .limits
+                    .max_fluid
+                    .map_or(i64::MAX, |max_fluid| max_fluid - container.fluid.h2o);
+                -container.solid.ice.min(room.max(0))
+            };
+            container.fluid.h2o -= moved;
+            container.solid.ice += moved;
+        }
+    }
+
+    fn apply_bursts(&mut self) {
+        self.last_bursts.clear();
+        let root = self.root;
+        for index in 0..self.containers.len() {
+            let id = ContainerId(index);
+            let container = &self.containers[index];
+            let pressure = container.pressure();
+            if id == root || container.max_pressure.is_none_or(|max| pressure <= max) {
+                continue;
+            }
+            let vented = container.gas.scale(BURST_VENT_NUM, BURST_VENT_DEN);
+            self.containers[index].gas.apply_delta(vented * -1);
+            self.containers[root.index()].gas.apply_delta(vented);
+            self.last_bursts.push(BurstEvent {
+                container: id,
+                pressure,
+            });
+        }
+    }
+
+    // Walks the tree from the root, so a parent settles with its own parent before its
+    // children. Each move is truncated toward zero, leaving a small gap that never closes.
+    fn apply_diffusion(&mut self) {
+        let rate = self.diffusion_rate as i128;
+        if rate == 0 {
+            return;
+        }
+        let mut stack = vec![self.root];
+        while let Some(parent) = stack.pop() {
+            let children = self.containers[parent.index()].children.clone();
+            for &child in &children {
+                let (from, to) = self.container_pair_mut(parent, child);
+                let mut moved = Gas::zero();
+                for species in Species::ALL {
+                    let equalize = Self::flow_amount(
+                        from.gas.amount(species),
+                        from.volume,
+                        to.gas.amount(species),
+                        to.volume,
+                        i64::MAX,
+                        i64::MAX,
+                    );
+                    *moved.amount_mut(species) =
+                        (equalize as i128 * rate / FULL_DIFFUSION as i128) as i64;
+                }
+                from.gas.apply_delta(moved * -1);
+                to.gas.apply_delta(moved);
+            }
+            stack.extend(children.into_iter().rev());
+        }
+    }
+
+    fn apply_leaks(&mut self) {
+        for leak in &self.leaks {
+            let gas = &mut self.containers[leak.container.index()].gas;
+            gas.apply_delta(Gas {
+                o2: -leak.rate.o2.min(gas.o2),
+                co2: -leak.rate.co2.min(gas.co2),
+                co: -leak.rate.co.min(gas.co),
+                h2o: -leak.rate.h2o.min(gas.h2o),
+            });
+        }
+    }
+
+    fn solve_pipe_network(&mut self, species: Species) {
+        let amounts: Vec<i64> = self
+            .containers
+            .iter()
+            .map(|container| container.gas.amount(species))
+            .collect();
+        let volumes: Vec<f64> = self
+            .containers
+            .iter()
+            .map(|container| container.volume.value() as f64)
+            .collect();
+
+        // Asymmetric pipes use the rate for the direction the gradient points at the start
+        // of the tick. Sorting the edges makes the floating point sums independent of pipe
+        // insertion order.
+        let mut edges: Vec<(usize, usize, f64, usize)> = self
+            .pipes
+            .iter()
+            .enumerate()
+            .filter(|(_, pipe)| pipe.open)
+            .map(|(pipe_index, pipe)| {
+                let (a, b) = (pipe.a.index(), pipe.b.index());
+                let forward = amounts[a] as f64 / volumes[a] >= amounts[b] as f64 / volumes[b];
+                let rate = if forward {
+                    pipe.flow_rate.amount(species)
+                } else {
+                    pipe.reverse_flow_rate.amount(species)
+                };
+                (a.min(b), a.max(b), rate as f64, pipe_index)
+            })
+            .filter(|&(_, _, rate, _)| rate > 0.0)
+            .collect();
+        if edges.is_empty() {
+            return;
+        }
+        edges.sort_by(|x, y| {
+            (x.0, x.1)
+                .cmp(&(y.0, y.1))
+                .then(x.2.total_cmp(&y.2))
+                .then(x.3.cmp(&y.3))
+        });
+
+        // Solve (V + L) x = -L c for the change x in concentration, where L is the
+        // conductance-weighted graph Laplacian and c = amount / volume.
+        let mut rhs = vec![0.0; amounts.len()];
+        let mut diagonal = volumes.clone();
+        for &(a, b, conductance, _) in &edges {
+            let concentration_a = amounts[a] as f64 / volumes[a];
+            let concentration_b = amounts[b] as f64 / volumes[b];
+            let flow = conductance * (concentration_a - concentration_b);
+            rhs[a] -= flow;
+            rhs[b] += flow;
+            diagonal[a] += conductance;
+            diagonal[b] += conductance;
+        }
+        let apply = |x: &[f64], out: &mut [f64]| {
+            for (i, value) in out.iter_mut().enumerate() {
+                *value = volumes[i] * x[i];
+            }
+            for &(a, b, conductance, _) in &edges {
+                let flow = conductance * (x[a] - x[b]);
+                out[a] += flow;
+                out[b] -= flow;
+            }
+        };
+        let change = conjugate_gradient(apply, &diagonal, &rhs);
+
+        // Backward Euler drives each pipe by the end-of-tick gradient. Rounded per pipe, these
+        // can be a mole off the container changes below, which are rounded per component.
+        for &(a, b, conductance, pipe_index) in &edges {
+            let concentration_a = amounts[a] as f64 / volumes[a] + change[a];
+            let concentration_b = amounts[b] as f64 / volumes[b] + change[b];
+            let flow = (conductance * (concentration_a - concentration_b)).round() as i64;
+            let pipe = &self.pipes[pipe_index];
+            let flow = if pipe.a.index() == a { flow } else { -flow };
+            *self.last_pipe_flows[pipe_index].amount_mut(species) = flow;
+        }
+
+        // The pipes conserve moles within each connected component, so round each component
+        // back to integers with the largest remainder method to keep its total exact.
+        let mut components = DisjointSet::new(amounts.len());
+        for &(a, b, _, _) in &edges {
+            components.union(a, b);
+        }
+        let mut members: Vec<Vec<usize>> = vec![Vec::new(); amounts.len()];
+        for &(a, b, _, _) in &edges {
+            for index in [a, b] {
+                let root = components.find(index);
+                if !members[root].contains(&index) {
+                    members[root].push(index);
+                }
+            }
+        }
+
+        for mut member in members.into_iter().filter(|member| !member.is_empty()) {
+            member.sort_unstable();
+            let total: i64 = member.iter().map(|&index| amounts[index]).sum();
+            let mut rounded: Vec<(usize, i64, f64)> = member
+                .iter()
+                .map(|&index| {
+                    let target = (amounts[index] as f64 + volumes[index] * change[index]).max(0.0);
+                    let floor = target.floor();
+                    (index, floor as i64, target - floor)
+                })
+                .collect();
+            let mut assigned: i64 = rounded.iter().map(|&(_, amount, _)| amount).sum();
+            rounded.sort_by(|x, y| y.2.total_cmp(&x.2).then(x.0.cmp(&y.0)));
+            // Flooring leaves a shortfall for the largest remainders. Clamping a slightly
+            // negative solution to zero can instead overshoot the total, which is taken back
+            // from the smallest remainders that still hold moles.
+            let mut ranks = (0..rounded.len()).cycle();
+            while assigned < total {
+                let rank = ranks.next().expect("components are never empty");
+                rounded[rank].1 += 1;
+                assigned += 1;
+            }
+            let mut ranks = (0..rounded.len()).rev().cycle();
+            while assigned > total {
+                let rank = ranks.next().expect("components are never empty");
+                if rounded[rank].1 > 0 {
+                    rounded[rank].1 -= 1;
+                    assigned -= 1;
+                }
+            }
+            for (index, amount, _) in rounded {
+                *self.containers[index].gas.amount_mut(species) = amount;
+            }
+        }
+    }
+
+    fn insert_container(
+        &mut self,
+        volume: Volume,
+        gas: Gas,
+        fluid: Fluid,
+        solid: Solid,
+        limits: ContainerLimits,
+    ) -> ContainerId {
+        let id = ContainerId(self.containers.len());
+        self.containers
+            .push(Container::new(volume, gas, fluid, solid, limits));
+        id
+    }
+
+    // Returns the gas moved from `pipe.a` to `pipe.b`.
+    fn apply_pipe_flow(&mut self, pipe: Pipe) -> Gas {
+        if !pipe.open {
+            return Gas::zero();
+        }
+        let (a, b) = self.container_pair_mut(pipe.a, pipe.b);
+        let mut delta = Gas::zero();
+        let mut inverse = Gas::zero();
+
+        let o2_flow = Self::flow_amount(
+            a.gas.o2,
+            a.volume,
+            b.gas.o2,
+            b.volume,
+            pipe.flow_rate.o2,
+            pipe.reverse_flow_rate.o2,
+        );
+        let co2_flow = Self::flow_amount(
+            a.gas.co2,
+            a.volume,
+            b.gas.co2,
+            b.volume,
+            pipe.flow_rate.co2,
+            pipe.reverse_flow_rate.co2,
+        );
+        let co_flow = Self::flow_amount(
+            a.gas.co,
+            a.volume,
+            b.gas.co,
+            b.volume,
+            pipe.flow_rate.co,
+            pipe.reverse_flow_rate.co,
+        );
+        let h2o_flow = Self::flow_amount(
+            a.gas.h2o,
+            a.volume,
+            b.gas.h2o,
+            b.volume,
+            pipe.flow_rate.h2o,
+            pipe.reverse_flow_rate.h2o,
+        );
+
+        delta.o2 = -o2_flow;
+        delta.co2 = -co2_flow;
+        delta.co = -co_flow;
+        delta.h2o = -h2o_flow;
+
+        inverse.o2 = o2_flow;
+        inverse.co2 = co2_flow;
+        inverse.co = co_flow;
+        inverse.h2o = h2o_flow;
+
+        a.gas.apply_delta(delta);
+        b.gas.apply_delta(inverse);
+        inverse
+    }
+
+    fn container_pair_mut(
+        &mut self,
+        a: ContainerId,
+        b: ContainerId,
+    ) -> (&mut Container, &mut Container) {
+        let a_idx = a.index();
+        let b_idx = b.index();
+        assert!(a_idx != b_idx, "container pair must be different");
+        if a_idx < b_idx {
+            let (left, right) = self.containers.split_at_mut(b_idx);
+            (&mut left[a_idx], &mut right[0])
+        } else {
+            let (left, right) = self.containers.split_at_mut(a_idx);
+            (&mut right[0], &mut left[b_idx])
+        }
+    }
+
+    fn flow_amount(
+        amount_a: i64,
+        volume_a: Volume,
+        amount_b: i64,
+        volume_b: Volume,
+        max_forward: i64,
+        max_reverse: i64,
+    ) -> i64 {
+        // Dividing both volumes by their gcd keeps the products small for huge containers
+        // like the atmosphere without changing the ratio.
+        let divisor = gcd(volume_a.value(), volume_b.value()).max(1);
+        let va = (volume_a.value() / divisor) as i128;
+        let vb = (volume_b.value() / divisor) as i128;
+        let numerator = (amount_a as i128)
+            .checked_mul(vb)
+            .zip((amount_b as i128).checked_mul(va))
+            .and_then(|(a, b)| a.checked_sub(b));
+        debug_assert!(numerator.is_some(), "pipe flow overflowed");
+        let Some(numerator) = numerator else {
+            return 0;
+        };
+        let denom = va + vb;
+        if denom <= 0 {
+            return 0;
+        }
+        let mut equalize = numerator / denom;
+        // Positive flow goes from a to b and is capped by the forward rate.
+        let hi = (amount_a as i128).min(max_forward as i128);
+        let lo = -(amount_b as i128).min(max_reverse as i128);
+        if equalize > hi {
+            equalize = hi;
+        } else if equalize < lo {
+            equalize = lo;
+        }
+        equalize as i64
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+// Jacobi-preconditioned conjugate gradient for the symmetric positive definite pipe systems.
+fn conjugate_gradient(
+    apply: impl Fn(&[f64], &mut [f64]),
+    diagonal: &[f64],
+    rhs: &[f64],
+) -> Vec<f64> {
+    let n = rhs.len();
+    let mut x = vec![0.0; n];
+    let mut residual = rhs.to_vec();
+    let mut preconditioned: Vec<f64> = residual.iter().zip(diagonal).map(|(r, d)| r / d).collect();
+    let mut direction = preconditioned.clone();
+    let mut product = vec![0.0; n];
+    let mut rho = dot(&residual, &preconditioned);
+    let tolerance = dot(rhs, rhs) * 1e-24;
+
+    for _ in 0..4 * n {
+        if dot(&residual, &residual) <= tolerance {
+            break;
+        }
+        apply(&direction, &mut product);
+        let alpha = rho / dot(&direction, &product);
+        for i in 0..n {
+            x[i] += alpha * direction[i];
+            residual[i] -= alpha * product[i];
+            preconditioned[i] = residual[i] / diagonal[i];
+        }
+        let next_rho = dot(&residual, &preconditioned);
+        let beta = next_rho / rho;
+        rho = next_rho;
+        for i in 0..n {
+            direction[i] = preconditioned[i] + beta * direction[i];
+        }
+    }
+    x
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+struct DisjointSet {
+    parents: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+        }
+    }
+
+    fn find(&mut self, index: usize) -> usize {
+        let mut root = index;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        self.parents[index] = root;
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        // Keep the lowest index as root so grouping is independent of union order.
+        self.parents[a.max(b)] = a.min(b);
+    }
+}
+
+// Runs one container's reactions, all of which must belong to it, for one tick.
+fn react(container: &mut Container, reactions: &[Reaction], scheduling: ReactionScheduling) {
+    match scheduling {
+        ReactionScheduling::Sequential => {
+            for reaction in reactions {
+                if let Some(planned) = reaction.planned(container) {
+                    planned.apply(container);
+                }
+            }
+        }
+        ReactionScheduling::Simultaneous => react_simultaneously(container, reactions),
+    }
+}
+
+fn react_simultaneously(container: &mut Container, reactions: &[Reaction]) {
+    let planned: Vec<Reaction> = reactions
+        .iter()
+        .filter_map(|reaction| reaction.planned(container))
+        .collect();
+
+    let available = container.amounts();
+    let mut demand = [0i64; 7];
+    for reaction in &planned {
+        for (total, component) in demand.iter_mut().zip(reaction.components()) {
+            *total += (-component).max(0);
+        }
+    }
+
+    for reaction in planned {
+        let steps = reaction.steps();
+        let fair_steps = reaction
+            .components()
+            .into_iter()
+            .zip(available)
+            .zip(demand)
+            .filter(|&((component, available), total)| component < 0 && total > available)
+            .map(|((_, available), total)| {
+                (steps as i128 * available as i128 / total as i128) as i64
+            })
+            .fold(steps, i64::min)
+            // Products of the earlier reactions may already have used up a cap.
+            .min(reaction.capacity_steps(container));
+        if fair_steps > 0 {
+            reaction.scaled(fair_steps).apply(container);
+        }
+    }
+}
+
+pub fn add_human(engine: &mut Engine, container: ContainerId, o2_per_tick: i64) {
+    add_human_full(engine, container, o2_per_tick, 0);
+}
+
+// Drinking is modelled as liquid water passing through and being exhaled as vapor.
+pub fn add_human_full(
+    engine: &mut Engine,
+    container: ContainerId,
+    o2_per_tick: i64,
+    water_per_tick: i64,
+) {
+    assert!(o2_per_tick >= 0, "o2_per_tick must be non-negative");
+    assert!(water_per_tick >= 0, "water_per_tick must be non-negative");
+    engine.add_reaction(
+        container,
+        Gas {
+            o2: -o2_per_tick,
+            co2: o2_per_tick,
+            co: 0,
+            h2o: o2_per_tick + water_per_tick,
+        },
+        Fluid {
+            h2o: -water_per_tick,
+        },
+        Solid {
+            ch2o: -o2_per_tick,
+            ice: 0,
+        },
+    );
+}
+
+// Carbon monoxide binds to haemoglobin, so the crew takes up less O2 (and drinks and exhales
+// proportionally less) as CO builds up: the rate halves at `half_rate_co_pressure`.
+pub fn add_human_with_co_poisoning(
+    engine: &mut Engine,
+    container: ContainerId,
+    o2_per_tick: i64,
+    water_per_tick: i64,
+    half_rate_co_pressure: i64,
+) {
+    assert!(o2_per_tick >= 0, "o2_per_tick must be non-negative");
+    assert!(water_per_tick >= 0, "water_per_tick must be non-negative");
+    engine.add_reaction_with_rate(
+        container,
         Gas {
             o2: -o2_per_tick,
             co2: o2_per_tick,
             co: 0,
-            h2o: o2_per_tick,
-        },
-        Fluid::zero(),
-        Solid { ch2o: -o2_per_tick },
-    );
-}
+            h2o: o2_per_tick + water_per_tick,
+        },
+        Fluid {
+            h2o: -water_per_tick,
+        },
+        Solid {
+            ch2o: -o2_per_tick,
+            ice: 0,
+        },
+        ReactionMode::AllOrNothing,
+        ReactionRate::InhibitedBy {
+            species: Species::Co,
+            half_rate_pressure: half_rate_co_pressure,
+        },
+    );
+}
+
+pub fn add_photosynthesis(engine: &mut Engine, container: ContainerId, co2_per_tick: i64) {
+    assert!(co2_per_tick >= 0, "co2_per_tick must be non-negative");
+    engine.add_reaction(
+        container,
+        Gas {
+            o2: co2_per_tick,
+            co2: -co2_per_tick,
+            co: 0,
+            h2o: 0,
+        },
+        Fluid { h2o: -co2_per_tick },
+        Solid {
+            ch2o: co2_per_tick,
+            ice: 0,
+        },
+    );
+}
+
+// Photosynthesis that slows in dim light and at low CO2, reaching half its light-limited
+// rate at `half_rate_co2_pressure`.
+pub fn add_photosynthesis_lit(
+    engine: &mut Engine,
+    container: ContainerId,
+    co2_per_tick: i64,
+    half_rate_co2_pressure: i64,
+) {
+    assert!(co2_per_tick >= 0, "co2_per_tick must be non-negative");
+    engine.add_reaction_with_rate(
+        container,
+        Gas {
+            o2: co2_per_tick,
+            co2: -co2_per_tick,
+            co: 0,
+            h2o: 0,
+        },
+        Fluid { h2o: -co2_per_tick },
+        Solid {
+            ch2o: co2_per_tick,
+            ice: 0,
+        },
+        ReactionMode::AllOrNothing,
+        ReactionRate::Lit {
+            species: Species::Co2,
+            half_rate_pressure: half_rate_co2_pressure,
+        },
+    );
+}
+
+// Life-support scrubber: CO2 + H2O(g) -> CH2O(s) + O2. There is no plain carbon solid, so the
+// fixed carbon leaves as CH2O, taking its hydrogen from cabin humidity rather than the liquid
+// water photosynthesis uses. The scrubber stalls in dry air.
+pub fn add_scrubber(engine: &mut Engine, container: ContainerId, co2_per_tick: i64) {
+    assert!(co2_per_tick >= 0, "co2_per_tick must be non-negative");
+    engine.add_reaction(
+        container,
+        Gas {
+            o2: co2_per_tick,
+            co2: -co2_per_tick,
+            co: 0,
+            h2o: -co2_per_tick,
+        },
+        Fluid::zero(),
+        Solid {
+            ch2o: co2_per_tick,
+            ice: 0,
+        },
+    );
+}
+
+pub fn add_moxie(engine: &mut Engine, container: ContainerId, co2_per_tick: i64) {
+    assert!(co2_per_tick >= 0, "co2_per_tick must be non-negative");
+    assert!(
+        co2_per_tick % 2 == 0,
+        "co2_per_tick must be even for 2 CO2 -> 2 CO + O2"
+    );
+    let o2_per_tick = co2_per_tick / 2;
+    engine.add_reaction(
+        container,
+        Gas {
+            o2: o2_per_tick,
+            co2: -co2_per_tick,
+            co: co2_per_tick,
+            h2o: 0,
+        },
+        Fluid::zero(),
+        Solid::zero(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gas_mix_matches_gas() {
+        let gas = Gas {
+            o2: 95,
+            co2: 40,
+            co: 3,
+            h2o: 7,
+        };
+        let mut mix = GasMix::from(gas);
+        assert_eq!(Gas::from(mix), gas);
+        assert_eq!(mix.amount(Species::Co), 3);
+        assert_eq!(mix.pressure(Volume::new(10)), gas.pressure(Volume::new(10)));
+
+        let mut delta = GasMix::zero();
+        delta.set_amount(Species::H2o, -8);
+        assert!(!mix.can_apply_delta(delta));
+        delta.set_amount(Species::H2o, -7);
+        assert!(mix.can_apply_delta(delta));
+        mix.apply_delta(delta);
+        assert_eq!(Gas::from(mix), Gas { h2o: 0, ..gas });
+    }
+
+    #[test]
+    fn scaled_mixes_sum_to_expected_totals() {
+        let air = Gas {
+            o2: 21,
+            co2: 1,
+            co: 0,
+            h2o: 3,
+        };
+        let exhaust = Gas {
+            o2: 0,
+            co2: 10,
+            co: 2,
+            h2o: 5,
+        };
+        let blend = air * 4 + exhaust.scale(1, 2);
+        assert_eq!(
+            blend,
+            Gas {
+                o2: 84,
+                co2: 9,
+                co: 1,
+                h2o: 14,
+            }
+        );
+        // Each species floors on its own: half of 25 moles floors to 12, but 10 + 0 + 0 + 1 remain.
+        assert_eq!(air.scale(1, 2).pressure_exact(Volume::new(1)), 11);
+        // Negative deltas truncate toward zero too, so halving -5 gives -2 rather than -3.
+        assert_eq!(
+            (exhaust * -1).scale(1, 2),
+            Gas {
+                o2: 0,
+                co2: -5,
+                co: -1,
+                h2o: -2,
+            }
+        );
+        assert_eq!(exhaust.scale(-1, 2), (exhaust * -1).scale(1, 2));
+    }
+
+    #[test]
+    fn transfer_gas_is_clamped_to_available_amount() {
+        let mut engine = Engine::new(Volume::new(100), Gas::zero(), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        let tank = engine.add_container(
+            root,
+            Volume::new(10),
+            Gas {
+                o2: 50,
+                co2: 5,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid::zero(),
+            Solid::zero(),
+        );
+
+        let requested = Gas {
+            o2: 20,
+            co2: 20,
+            co: 20,
+            h2o: 0,
+        };
+        let moved = engine.transfer_gas(tank, root, requested);
+
+        assert_eq!(
+            moved,
+            Gas {
+                o2: 20,
+                co2: 5,
+                co: 0,
+                h2o: 0,
+            }
+        );
+        assert_eq!(
+            engine.container(tank).gas,
+            Gas {
+                o2: 30,
+                co2: 0,
+                co: 0,
+                h2o: 0,
+            }
+        );
+        assert_eq!(engine.container(root).gas, moved);
+    }
+
+    #[test]
+    fn time_to_empty_counts_ticks_of_steady_co2_outflow() {
+        let mut engine = Engine::new(
+            Volume::new(1_000_000),
+            Gas::zero(),
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        let root = engine.root();
+        let habitat = engine.add_container(
+            root,
+            Volume::new(10),
+            Gas {
+                o2: 0,
+                co2: 100,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid { h2o: 1_000 },
+            Solid::zero(),
+        );
+        engine.add_pipe(
+            habitat,
+            root,
+            Gas {
+                o2: 0,
+                co2: 4,
+                co: 0,
+                h2o: 0,
+            },
+        );
+        // Pipe flow floors towards equilibrium, so the reaction consumes the last few moles.
+        add_photosynthesis(&mut engine, habitat, 1);
+
+        let ticks = engine.time_to_empty(habitat, Species::Co2, 1_000);
+        assert!(matches!(ticks, Some(20..=100)), "ticks={ticks:?}");
+        assert_eq!(engine.time_to_empty(habitat, Species::Co2, 10), None);
+        assert_eq!(
+            engine.container(habitat).gas.co2,
+            100,
+            "projection must not tick the engine"
+        );
+    }
+
+    #[test]
+    fn time_to_empty_is_none_with_net_inflow() {
+        let mut engine = Engine::new(Volume::new(10), Gas::zero(), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        let habitat = engine.add_container(
+            root,
+            Volume::new(10),
+            Gas {
+                o2: 100,
+                co2: 10,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid::zero(),
+            Solid {
+                ch2o: 1_000,
+                ice: 0,
+            },
+        );
+        add_human(&mut engine, habitat, 1);
+
+        assert_eq!(engine.time_to_empty(habitat, Species::Co2, 1_000), None);
+        assert_eq!(engine.time_to_empty(habitat, Species::H2o, 0), Some(0));
+        assert_eq!(engine.time_to_empty(habitat, Species::O2, 1_000), Some(100));
+        assert_eq!(engine.time_to_empty(habitat, Species::O2, 100), Some(100));
+    }
+
+    #[test]
+    fn time_to_empty_gives_up_on_a_slow_drain_of_a_big_store() {
+        let atmosphere = Gas {
+            co2: 1_000_000_000_000,
+            ..Gas::zero()
+        };
+        let (fluid, solid) = (Fluid::zero(), Solid::zero());
+        let mut engine = Engine::new(Volume::new(1_000_000), atmosphere, fluid, solid);
+        let root = engine.root();
+        let leak = Gas {
+            co2: 3,
+            ..Gas::zero()
+        };
+        engine.add_leak(root, leak);
+        // Emptying would take over 300 billion ticks.
+        assert_eq!(engine.time_to_empty(root, Species::Co2, 10_000), None);
+    }
+
+    fn replay_engine() -> Engine {
+        let mut engine = Engine::new(
+            Volume::new(1_000),
+            Gas {
+                o2: 13_000,
+                co2: 953_000,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        let root = engine.root();
+        let habitat = engine.add_container(
+            root,
+            Volume::new(100),
+            Gas {
+                o2: 20_200,
+                co2: 80_800,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid { h2o: 400 },
+            Solid { ch2o: 500, ice: 0 },
+        );
+        let greenhouse = engine.add_container(
+            root,
+            Volume::new(50),
+            Gas {
+                o2: 1_000,
+                co2: 40_000,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid { h2o: 2_000 },
+            Solid::zero(),
+        );
+        engine.add_pipe(
+            habitat,
+            root,
+            Gas {
+                o2: 0,
+                co2: 0,
+                co: 2,
+                h2o: 0,
+            },
+        );
+        engine.add_pipe(
+            habitat,
+            greenhouse,
+            Gas {
+                o2: 5,
+                co2: 5,
+                co: 5,
+                h2o: 5,
+            },
+        );
+        add_human(&mut engine, habitat, 3);
+        add_photosynthesis(&mut engine, greenhouse, 2);
+        add_moxie(&mut engine, habitat, 2);
+        engine
+    }
+
+    #[test]
+    fn identical_engines_stay_identical_over_many_ticks() {
+        let mut a = replay_engine();
+        let mut b = replay_engine();
+        for _ in 0..1_000 {
+            a.tick();
+            b.tick();
+        }
+        assert_eq!(a, b);
+        assert_ne!(a, replay_engine(), "ticking should change the state");
+    }
+
+    #[test]
+    fn gas_totals_fractions_and_partial_pressures() {
+        let gas = Gas {
+            o2: 210,
+            co2: 40,
+            co: 0,
+            h2o: 750,
+        };
+        assert_eq!(gas.total(), 1000);
+        assert_eq!(gas.fraction(Species::O2), 0.21);
+        assert_eq!(gas.fraction(Species::Co), 0.0);
+        let sum: f32 = Species::ALL
+            .iter()
+            .map(|&species| gas.fraction(species))
+            .sum();
+        assert!((sum - 1.0).abs() < 1e-6, "sum={sum}");
+        assert_eq!(Gas::zero().fraction(Species::O2), 0.0);
+
+        let engine = Engine::new(Volume::new(10), gas, Fluid::zero(), Solid::zero());
+        assert_eq!(
+            engine.container(engine.root()).gas_pressure_breakdown(),
+            [
+                (Species::O2, 21),
+                (Species::Co2, 4),
+                (Species::Co, 0),
+                (Species::H2o, 75)
+            ]
+        );
+    }
+
+    #[test]
+    fn masses_follow_molar_masses_and_reactions_conserve_them() {
+        let gas = Gas {
+            o2: 10,
+            co2: 2,
+            co: 1,
+            h2o: 3,
+        };
+        assert_eq!(gas.species_mass_grams(Species::Co2), 88);
+        assert_eq!(gas.mass_grams(), 320 + 88 + 28 + 54);
+        assert_eq!(Fluid { h2o: 5 }.mass_grams(), 90);
+        assert_eq!(Solid { ch2o: 4, ice: 1 }.mass_grams(), 138);
+
+        let solid = Solid { ch2o: 50, ice: 0 };
+        let mut engine = Engine::new(Volume::new(10), gas, Fluid { h2o: 100 }, solid);
+        let root = engine.root();
+        add_human(&mut engine, root, 2);
+        add_photosynthesis(&mut engine, root, 1);
+        add_moxie(&mut engine, root, 2);
+        let mass = |engine: &Engine| engine.container(engine.root()).mass_grams();
+        let before = mass(&engine);
+        for _ in 0..5 {
+            engine.tick();
+            assert_eq!(mass(&engine), before);
+        }
+        assert_ne!(engine.container(root).gas(), gas);
+    }
+
+    #[test]
+    fn pressure_exact_avoids_per_species_truncation() {
+        let volume = Volume::new(10);
+        let mix = Gas {
+            o2: 9,
+            co2: 9,
+            co: 9,
+            h2o: 9,
+        };
+        assert_eq!(mix.pressure(volume), 0);
+        assert_eq!(mix.pressure_exact(volume), 3);
+
+        let multiples = Gas {
+            o2: 20,
+            co2: 30,
+            co: 40,
+            h2o: 50,
+        };
+        assert_eq!(multiples.pressure(volume), multiples.pressure_exact(volume));
+    }
+
+    fn parallel_pipe_engine(swap: bool) -> Engine {
+        let mut engine = Engine::new(Volume::new(30), Gas::zero(), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        let tank = engine.add_container(
+            root,
+            Volume::new(10),
+            Gas {
+                o2: 1_000,
+                co2: 777,
+                co: 0,
+                h2o: 13,
+            },
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        let slow = Gas {
+            o2: 1,
+            co2: 2,
+            co: 0,
+            h2o: 1,
+        };
+        let fast = Gas {
+            o2: 7,
+            co2: 3,
+            co: 0,
+            h2o: 5,
+        };
+        let (first, second) = if swap { (fast, slow) } else { (slow, fast) };
+        engine.add_pipe(tank, root, first);
+        engine.add_pipe(root, tank, second);
+        engine
+    }
+
+    #[test]
+    fn implicit_tick_is_independent_of_pipe_order() {
+        let mut a = parallel_pipe_engine(false);
+        let mut b = parallel_pipe_engine(true);
+        let total = a.container(a.root()).gas.o2 + a.container(ContainerId(1)).gas.o2;
+        let atoms = a.atom_inventory();
+        for _ in 0..10 {
+            a.tick_implicit();
+            b.tick_implicit();
+            assert_eq!(a.containers, b.containers);
+            assert_eq!(a.check_conserved(&atoms), Ok(()));
+        }
+        let tank = a.container(ContainerId(1)).gas;
+        let root = a.container(a.root()).gas;
+        assert_eq!(tank.o2 + root.o2, total, "moles must be conserved");
+        // Three times the volume ends up with three times the moles once settled.
+        assert!(
+            (root.o2 - 3 * tank.o2).abs() <= 3,
+            "root={root:?}, tank={tank:?}"
+        );
+    }
+
+    #[test]
+    fn implicit_tick_conserves_atoms_through_stiff_pipes() {
+        let gas = |o2, co2| Gas {
+            o2,
+            co2,
+            co: 0,
+            h2o: 0,
+        };
+        let mut engine = Engine::new(Volume::new(7), gas(1_001, 3), Fluid::zero(), Solid::zero());
+        let mut previous = engine.root();
+        for volume in 1..6 {
+            let room = engine.add_container(
+                previous,
+                Volume::new(volume),
+                gas(0, 0),
+                Fluid::zero(),
+                Solid::zero(),
+            );
+            // Conductances far above the volumes push the solver toward tiny negative amounts.
+            engine.add_pipe(previous, room, gas(1_000_000, 1_000_000));
+            previous = room;
+        }
+        let atoms = engine.atom_inventory();
+        for _ in 0..20 {
+            engine.tick_implicit();
+            assert_eq!(engine.check_conserved(&atoms), Ok(()));
+            assert!(engine
+                .containers
+                .iter()
+                .all(|room| room.gas.is_non_negative()));
+        }
+    }
+
+    #[test]
+    fn pump_fills_tank_past_source_pressure() {
+        let mut engine = Engine::new(
+            Volume::new(100),
+            Gas {
+                o2: 1_000,
+                co2: 0,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        let root = engine.root();
+        let tank = engine.add_container(
+            root,
+            Volume::new(10),
+            Gas::zero(),
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        engine.add_pump(
+            root,
+            tank,
+            Gas {
+                o2: 50,
+                co2: 0,
+                co: 0,
+                h2o: 0,
+            },
+        );
+
+        for _ in 0..10 {
+            engine.tick();
+        }
+        assert_eq!(engine.container(tank).gas.o2, 500);
+        assert_eq!(engine.container(root).gas.o2, 500);
+        assert!(engine.container(tank).pressure() > engine.container(root).pressure());
+    }
+
+    fn starving_habitat(mode: ReactionMode) -> Engine {
+        let mut engine = Engine::new(Volume::new(10), Gas::zero(), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        let habitat = engine.add_container(
+            root,
+            Volume::new(10),
+            Gas {
+                o2: 2,
+                co2: 0,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid::zero(),
+            Solid { ch2o: 100, ice: 0 },
+        );
+        engine.add_reaction_with_mode(
+            habitat,
+            Gas {
+                o2: -3,
+                co2: 3,
+                co: 0,
+                h2o: 3,
+            },
+            Fluid::zero(),
+            Solid { ch2o: -3, ice: 0 },
+            mode,
+        );
+        engine
+    }
+
+    #[test]
+    fn saturating_reaction_consumes_what_is_left() {
+        let mut engine = starving_habitat(ReactionMode::Saturating);
+        engine.tick();
+        let habitat = engine.container(ContainerId(1));
+        assert_eq!(
+            habitat.gas,
+            Gas {
+                o2: 0,
+                co2: 2,
+                co: 0,
+                h2o: 2,
+            }
+        );
+        assert_eq!(habitat.solid, Solid { ch2o: 98, ice: 0 });
+    }
+
+    #[test]
+    fn all_or_nothing_reaction_skips_when_short() {
+        let mut engine = starving_habitat(ReactionMode::AllOrNothing);
+        let before = engine.clone();
+        engine.tick();
+        assert_eq!(engine, before);
+    }
+
+    #[test]
+    fn saturating_reaction_keeps_whole_balanced_steps() {
+        let mut engine = Engine::new(
+            Volume::new(10),
+            Gas {
+                o2: 0,
+                co2: 5,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        let root = engine.root();
+        // 2 CO2 -> 2 CO + O2 can only run in steps of two CO2.
+        engine.add_reaction_with_mode(
+            root,
+            Gas {
+                o2: 4,
+                co2: -8,
+                co: 8,
+                h2o: 0,
+            },
+            Fluid::zero(),
+            Solid::zero(),
+            ReactionMode::Saturating,
+        );
+        engine.tick();
+        assert_eq!(
+            engine.container(root).gas,
+            Gas {
+                o2: 2,
+                co2: 1,
+                co: 4,
+                h2o: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn human_drinks_water_and_exhales_it() {
+        let mut engine = Engine::new(
+            Volume::new(10),
+            Gas {
+                o2: 100,
+                co2: 0,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid { h2o: 50 },
+            Solid { ch2o: 100, ice: 0 },
+        );
+        let root = engine.root();
+        add_human_full(&mut engine, root, 3, 2);
+        engine.tick();
+
+        let container = engine.container(root);
+        assert_eq!(
+            container.gas,
+            Gas {
+                o2: 97,
+                co2: 3,
+                co: 0,
+                h2o: 5,
+            }
+        );
+        assert_eq!(container.fluid, Fluid { h2o: 48 });
+        assert_eq!(container.solid, Solid { ch2o: 97, ice: 0 });
+    }
+
+    #[test]
+    fn pipes_conserve_total_gas() {
+        let mut engine = replay_engine();
+        engine.reactions.clear();
+        let before = engine.stats();
+        assert_eq!(before.container_count, 3);
+        assert_eq!(before.pipe_count, 2);
+        assert_eq!(before.reaction_count, 0);
+
+        for _ in 0..100 {
+            engine.tick();
+        }
+        assert_eq!(engine.stats(), before);
+    }
+
+    #[test]
+    fn asymmetric_pipe_flows_slower_in_reverse() {
+        let o2 = |o2| Gas {
+            o2,
+            co2: 0,
+            co: 0,
+            h2o: 0,
+        };
+        let transfer = |a_o2, b_o2| {
+            let (volume, fluid, solid) = (Volume::new(10), Fluid::zero(), Solid::zero());
+            let mut engine = Engine::new(volume, Gas::zero(), fluid, solid);
+            let root = engine.root();
+            let a = engine.add_container(root, volume, o2(a_o2), fluid, solid);
+            let b = engine.add_container(root, volume, o2(b_o2), fluid, solid);
+            engine.add_asymmetric_pipe(a, b, o2(10), o2(2));
+            engine.tick();
+            (engine.container(a).gas.o2, engine.container(b).gas.o2)
+        };
+
+        assert_eq!(transfer(100, 0), (90, 10));
+        assert_eq!(transfer(0, 100), (2, 98));
+    }
+
+    #[test]
+    #[should_panic(expected = "pipe endpoint container 7 does not exist (engine has 1 containers)")]
+    fn add_pipe_rejects_unknown_container() {
+        let mut engine = Engine::new(Volume::new(10), Gas::zero(), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        engine.add_pipe(root, ContainerId(7), Gas::zero());
+    }
+
+    #[test]
+    #[should_panic(expected = "parent container 3 does not exist")]
+    fn add_container_rejects_unknown_parent() {
+        let mut engine = Engine::new(Volume::new(10), Gas::zero(), Fluid::zero(), Solid::zero());
+        engine.add_container(
+            ContainerId(3),
+            Volume::new(10),
+            Gas::zero(),
+            Fluid::zero(),
+            Solid::zero(),
+        );
+    }
+
+    #[test]
+    fn ticking_conserves_atoms() {
+        let mut engine = replay_engine();
+        let before = engine.atom_inventory();
+        for _ in 0..100 {
+            engine.tick();
+        }
+        engine.assert_conserved(&before);
+    }
+
+    #[test]
+    fn alarms_trip_as_the_crew_breathes_without_changing_the_engine() {
+        let gas = Gas {
+            o2: 300,
+            co2: 100,
+            co: 0,
+            h2o: 0,
+        };
+        let food = Solid { ch2o: 1000, ice: 0 };
+        let mut engine = Engine::new(Volume::new(10), gas, Fluid::zero(), food);
+        let root = engine.root();
+        add_human(&mut engine, root, 50);
+        let mut silent = engine.clone();
+        engine.add_alarm(
+            root,
+            Alarm {
+                o2_min: Some(20),
+                co2_max: Some(20),
+                ..Alarm::default()
+            },
+        );
+        engine.add_alarm(
+            root,
+            Alarm {
+                pressure_min: Some(1),
+                ..Alarm::default()
+            },
+        );
+        assert_eq!(engine.check_alarms(), Vec::new());
 
-pub fn add_photosynthesis(
-    engine: &mut Engine,
-    container: ContainerId,
-    co2_per_tick: i64,
-) {
-    assert!(co2_per_tick >= 0, "co2_per_tick must be non-negative");
-    engine.add_reaction(
-        container,
-        Gas {
-            o2: co2_per_tick,
-            co2: -co2_per_tick,
+        // 300 - 50 * 3 = 150 O2 is partial pressure 15; 100 + 150 CO2 is 25.
+        for _ in 0..3 {
+            engine.tick();
+            silent.tick();
+        }
+        let tripped = |condition| TrippedAlarm {
+            alarm: 0,
+            container: root,
+            condition,
+        };
+        assert_eq!(
+            engine.check_alarms(),
+            vec![
+                tripped(AlarmCondition::LowO2),
+                tripped(AlarmCondition::HighCo2)
+            ]
+        );
+        assert_eq!(engine.container(root), silent.container(root));
+    }
+
+    #[test]
+    fn try_add_reaction_reports_problems_instead_of_panicking() {
+        let mut engine = Engine::new(Volume::new(10), Gas::zero(), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        let o2 = |o2| Gas {
+            o2,
+            co2: 0,
             co: 0,
             h2o: 0,
-        },
-        Fluid { h2o: -co2_per_tick },
-        Solid { ch2o: co2_per_tick },
-    );
-}
+        };
+        let burn = Gas {
+            o2: -1,
+            co2: 1,
+            co: 0,
+            h2o: 0,
+        };
+        let fire = Solid { ch2o: -1, ice: 0 };
 
-pub fn add_moxie(engine: &mut Engine, container: ContainerId, co2_per_tick: i64) {
-    assert!(co2_per_tick >= 0, "co2_per_tick must be non-negative");
-    assert!(
-        co2_per_tick % 2 == 0,
-        "co2_per_tick must be even for 2 CO2 -> 2 CO + O2"
-    );
-    let o2_per_tick = co2_per_tick / 2;
-    engine.add_reaction(
-        container,
-        Gas {
-            o2: o2_per_tick,
-            co2: -co2_per_tick,
-            co: co2_per_tick,
+        assert_eq!(
+            engine.try_add_reaction(root, o2(1), Fluid::zero(), Solid::zero()),
+            Err(ReactionError::Unbalanced {
+                carbon: 0,
+                hydrogen: 0,
+                oxygen: 2
+            })
+        );
+        assert_eq!(
+            engine.try_add_reaction(ContainerId(4), o2(0), Fluid::zero(), Solid::zero()),
+            Err(ReactionError::UnknownContainer(ContainerId(4)))
+        );
+        let inhibited = ReactionRate::InhibitedBy {
+            species: Species::O2,
+            half_rate_pressure: 0,
+        };
+        assert_eq!(
+            engine.try_add_reaction_with_rate(
+                root,
+                o2(0),
+                Fluid::zero(),
+                Solid::zero(),
+                ReactionMode::AllOrNothing,
+                inhibited
+            ),
+            Err(ReactionError::NonPositiveHalfRatePressure(0))
+        );
+        assert!(engine.reactions_for(root).is_empty());
+
+        // CH2O + O2 -> CO2 forgets the water; adding it back gets the reaction an id.
+        let error = engine
+            .try_add_reaction(root, burn, Fluid::zero(), fire)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "reaction is not atom-balanced (C +0, H -2, O -1)"
+        );
+        let mut balanced = burn;
+        balanced.h2o = 1;
+        assert_eq!(
+            engine.try_add_reaction(root, balanced, Fluid::zero(), fire),
+            Ok(ReactionId(0))
+        );
+    }
+
+    #[test]
+    fn reactions_for_lists_only_that_containers_reactions() {
+        let mut engine = Engine::new(Volume::new(10), Gas::zero(), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        let greenhouse = engine.add_container(
+            root,
+            Volume::new(5),
+            Gas::zero(),
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        add_human(&mut engine, root, 3);
+        add_photosynthesis(&mut engine, greenhouse, 2);
+
+        let reactions = engine.reactions_for(greenhouse);
+        assert_eq!(reactions.len(), 1);
+        assert_eq!(
+            reactions[0].summary(),
+            "-2 CO2, -2 H2O(l), +2 O2, +2 CH2O(s)"
+        );
+        assert_eq!(engine.reactions_for(root)[0].gas_delta.o2, -3);
+    }
+
+    #[test]
+    fn atom_counts_weigh_as_much_as_every_species() {
+        // Fails for any species whose atoms `AtomCount::of` misses.
+        let weight = |atoms: AtomCount| 12 * atoms.carbon + atoms.hydrogen + 16 * atoms.oxygen;
+        for species in Species::ALL {
+            let mut gas = Gas::zero();
+            *gas.amount_mut(species) = 1;
+            let atoms = AtomCount::of(gas, Fluid::zero(), Solid::zero());
+            assert_eq!(weight(atoms), species.molar_mass(), "{}", species.symbol());
+        }
+        let water = AtomCount::of(Gas::zero(), Fluid { h2o: 1 }, Solid::zero());
+        assert_eq!(weight(water), MOLAR_MASS_H2O);
+        let food = Solid { ch2o: 1, ice: 0 };
+        let ice = Solid { ch2o: 0, ice: 1 };
+        let food = AtomCount::of(Gas::zero(), Fluid::zero(), food);
+        let ice = AtomCount::of(Gas::zero(), Fluid::zero(), ice);
+        assert_eq!(weight(food), MOLAR_MASS_CH2O);
+        assert_eq!(weight(ice), MOLAR_MASS_H2O);
+    }
+
+    #[test]
+    fn leaks_show_up_as_conservation_drift() {
+        let mut engine = Engine::new(
+            Volume::new(10),
+            Gas {
+                o2: 50,
+                co2: 0,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        let before = engine.atom_inventory();
+        let root = engine.root();
+        engine.add_leak(
+            root,
+            Gas {
+                o2: 5,
+                co2: 0,
+                co: 0,
+                h2o: 0,
+            },
+        );
+        engine.tick();
+        assert_eq!(
+            engine.check_conserved(&before),
+            Err(AtomCount {
+                carbon: 0,
+                hydrogen: 0,
+                oxygen: -10,
+            })
+        );
+    }
+
+    #[test]
+    fn leaking_container_drains_to_vacuum() {
+        let mut engine = Engine::new(
+            Volume::new(10),
+            Gas {
+                o2: 95,
+                co2: 40,
+                co: 0,
+                h2o: 7,
+            },
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        let root = engine.root();
+        engine.add_leak(
+            root,
+            Gas {
+                o2: 10,
+                co2: 10,
+                co: 10,
+                h2o: 10,
+            },
+        );
+
+        let mut pressure = engine.container(root).pressure();
+        for _ in 0..10 {
+            engine.tick();
+            let next = engine.container(root).pressure();
+            assert!(next <= pressure, "pressure rose from {pressure} to {next}");
+            pressure = next;
+        }
+        assert_eq!(engine.container(root).gas, Gas::zero());
+    }
+
+    #[test]
+    fn reactions_slow_down_to_fit_a_solid_cap() {
+        let mut engine = Engine::new(Volume::new(10), Gas::zero(), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        let greenhouse = engine.add_container_with_limits(
+            root,
+            Volume::new(10),
+            Gas {
+                o2: 0,
+                co2: 100,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid { h2o: 100 },
+            Solid { ch2o: 8, ice: 0 },
+            ContainerLimits {
+                max_fluid: None,
+                max_solid: Some(10),
+            },
+        );
+        add_photosynthesis(&mut engine, greenhouse, 3);
+        let atoms = engine.atom_inventory();
+
+        // Only two of the three steps fit, and the water for the third stays liquid.
+        engine.tick();
+        assert_eq!(
+            engine.container(greenhouse).solid,
+            Solid { ch2o: 10, ice: 0 }
+        );
+        engine.tick();
+        assert_eq!(
+            engine.container(greenhouse).solid,
+            Solid { ch2o: 10, ice: 0 }
+        );
+        assert_eq!(engine.container(greenhouse).fluid, Fluid { h2o: 98 });
+        assert_eq!(engine.check_conserved(&atoms), Ok(()));
+    }
+
+    #[test]
+    fn reactions_slow_down_to_fit_a_fluid_cap() {
+        let mut engine = Engine::new(Volume::new(10), Gas::zero(), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        let tank = engine.add_container_with_limits(
+            root,
+            Volume::new(10),
+            Gas::zero(),
+            Fluid { h2o: 5 },
+            Solid::zero(),
+            ContainerLimits {
+                max_fluid: Some(6),
+                max_solid: None,
+            },
+        );
+        // A condenser turning vapor back into liquid water.
+        engine.add_reaction(
+            tank,
+            Gas {
+                o2: 0,
+                co2: 0,
+                co: 0,
+                h2o: -4,
+            },
+            Fluid { h2o: 4 },
+            Solid::zero(),
+        );
+        engine.container_mut(tank).gas.h2o = 8;
+        let atoms = engine.atom_inventory();
+
+        engine.tick();
+        assert_eq!(engine.container(tank).fluid, Fluid { h2o: 6 });
+        assert_eq!(engine.container(tank).gas.h2o, 7);
+        assert_eq!(engine.check_conserved(&atoms), Ok(()));
+
+        // Under simultaneous scheduling the first condenser fills the tank for both.
+        engine.add_reaction(
+            tank,
+            Gas {
+                o2: 0,
+                co2: 0,
+                co: 0,
+                h2o: -1,
+            },
+            Fluid { h2o: 1 },
+            Solid::zero(),
+        );
+        engine.container_mut(tank).fluid.h2o = 4;
+        engine.set_reaction_scheduling(ReactionScheduling::Simultaneous);
+        let atoms = engine.atom_inventory();
+        engine.tick();
+        assert_eq!(engine.container(tank).fluid, Fluid { h2o: 6 });
+        assert_eq!(engine.check_conserved(&atoms), Ok(()));
+    }
+
+    #[test]
+    fn carbon_monoxide_slows_human_o2_uptake() {
+        let o2_consumed = |co| {
+            let mut engine =
+                Engine::new(Volume::new(10), Gas::zero(), Fluid::zero(), Solid::zero());
+            let root = engine.root();
+            let habitat = engine.add_container(
+                root,
+                Volume::new(10),
+                Gas {
+                    o2: 1000,
+                    co2: 0,
+                    co,
+                    h2o: 0,
+                },
+                Fluid::zero(),
+                Solid { ch2o: 1000, ice: 0 },
+            );
+            add_human_with_co_poisoning(&mut engine, habitat, 8, 0, 5);
+            let before = engine.atom_inventory();
+            engine.tick();
+            assert_eq!(engine.atom_inventory(), before);
+            1000 - engine.container(habitat).gas.o2
+        };
+
+        assert_eq!(o2_consumed(0), 8);
+        // 50 moles in 10 volume is a partial pressure of 5, the half-rate point.
+        assert_eq!(o2_consumed(50), 4);
+        assert_eq!(o2_consumed(150), 2);
+    }
+
+    #[test]
+    fn describe_reports_partial_pressures_and_o2_fraction() {
+        let mut engine = Engine::new(Volume::new(10), Gas::zero(), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        let habitat = engine.add_container(
+            root,
+            Volume::new(100),
+            Gas {
+                o2: 20_000,
+                co2: 80_000,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid { h2o: 7 },
+            Solid { ch2o: 500, ice: 0 },
+        );
+        let report = engine
+            .container(habitat)
+            .describe(PressureScale::new(100.0));
+        assert_eq!(
+            report,
+            "volume: 100\n\
+             pressure: 100.00 kPa\n\
+             \x20 O2: 20.00 kPa\n\
+             \x20 CO2: 80.00 kPa\n\
+             \x20 CO: 0.00 kPa\n\
+             \x20 H2O: 0.00 kPa\n\
+             O2 mole fraction: 20.0%\n\
+             fluid H2O: 7\n\
+             solid CH2O: 500\n\
+             ice: 0"
+        );
+    }
+
+    #[test]
+    fn scrubber_turns_co2_and_humidity_into_o2_and_solid() {
+        let mut engine = Engine::new(
+            Volume::new(10),
+            Gas {
+                o2: 0,
+                co2: 50,
+                co: 0,
+                h2o: 6,
+            },
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        let root = engine.root();
+        add_scrubber(&mut engine, root, 4);
+        let before = engine.atom_inventory();
+        engine.tick();
+        let container = engine.container(root);
+        assert_eq!(
+            (container.gas.o2, container.gas.co2, container.gas.h2o),
+            (4, 46, 2)
+        );
+        assert_eq!(container.solid.ch2o, 4);
+        engine.assert_conserved(&before);
+
+        // Only 2 moles of vapour are left, so the all-or-nothing reaction stalls.
+        engine.tick();
+        assert_eq!(engine.container(root).gas.co2, 46);
+    }
+
+    #[test]
+    fn water_freezes_and_melts_up_to_capacity() {
+        let mut engine = Engine::new(Volume::new(10), Gas::zero(), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        let limits = ContainerLimits {
+            max_fluid: Some(40),
+            max_solid: Some(45),
+        };
+        let tank = engine.add_container_with_limits(
+            root,
+            Volume::new(10),
+            Gas::zero(),
+            Fluid { h2o: 30 },
+            Solid { ch2o: 0, ice: 25 },
+            limits,
+        );
+        engine.add_freeze_thaw(tank, 273);
+        let before = engine.atom_inventory();
+
+        // Only 20 of the 30 moles fit under the solid cap; the rest stays liquid.
+        engine.set_temperature(tank, 200);
+        engine.tick();
+        assert_eq!(engine.container(tank).fluid.h2o, 10);
+        assert_eq!(engine.container(tank).solid.ice, 45);
+        engine.tick();
+        assert_eq!(engine.container(tank).fluid.h2o, 10);
+
+        engine.set_temperature(tank, 273);
+        engine.tick();
+        assert_eq!(engine.container(tank).fluid.h2o, 40);
+        assert_eq!(engine.container(tank).solid.ice, 15);
+        engine.assert_conserved(&before);
+    }
+
+    #[test]
+    fn flow_amount_handles_extreme_amounts_and_volumes() {
+        let atmosphere = Volume::new(93_000_000_000_000);
+        let huge = Volume::new(i64::MAX);
+        assert_eq!(
+            Engine::flow_amount(i64::MAX, atmosphere, 0, atmosphere, i64::MAX, i64::MAX),
+            i64::MAX / 2
+        );
+        assert_eq!(
+            Engine::flow_amount(i64::MAX, huge, i64::MAX, huge, i64::MAX, i64::MAX),
+            0
+        );
+        assert_eq!(
+            Engine::flow_amount(0, huge, i64::MAX, Volume::new(1), i64::MAX, i64::MAX),
+            -(i64::MAX - 1)
+        );
+    }
+
+    fn merge_split_fixture() -> (Engine, [ContainerId; 4]) {
+        let gas = |o2, co2| Gas {
+            o2,
+            co2,
+            co: 0,
             h2o: 0,
-        },
-        Fluid::zero(),
-        Solid::zero(),
-    );
+        };
+        let food = |ch2o| Solid { ch2o, ice: 0 };
+        let mut engine = Engine::new(Volume::new(100), gas(500, 50), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        let tank = engine.add_container(
+            root,
+            Volume::new(10),
+            gas(90, 10),
+            Fluid { h2o: 7 },
+            food(3),
+        );
+        let room =
+            engine.add_container(root, Volume::new(30), gas(300, 30), Fluid::zero(), food(9));
+        let closet = engine.add_container(room, Volume::new(5), gas(40, 5), Fluid::zero(), food(1));
+        engine.add_pipe(tank, room, gas(4, 4));
+        engine.add_pipe(room, root, gas(2, 2));
+        engine.add_leak(closet, gas(1, 0));
+        add_human(&mut engine, room, 1);
+        (engine, [root, tank, room, closet])
+    }
+
+    #[test]
+    fn merging_conserves_totals_and_rewires_references() {
+        let (mut engine, [root, tank, room, closet]) = merge_split_fixture();
+        engine.set_max_pressure(closet, Some(1));
+        engine.tick();
+        assert_eq!(engine.last_bursts()[0].container, closet);
+        let atoms = engine.atom_inventory();
+        let total_volume = |engine: &Engine| -> i64 {
+            engine
+                .container_ids()
+                .map(|id| engine.container(id).volume().value())
+                .sum()
+        };
+        let volume = total_volume(&engine);
+
+        let merged = engine.merge_containers(room, tank);
+        assert_eq!(merged, ContainerId(1));
+        assert_eq!(engine.check_conserved(&atoms), Ok(()));
+        assert_eq!(total_volume(&engine), volume);
+        assert_eq!(engine.container(merged).volume(), Volume::new(40));
+        assert_eq!(engine.container(merged).fluid(), Fluid { h2o: 7 });
+
+        // The tank-room pipe became a loop and is gone; ids above the tank shifted down.
+        let closet = ContainerId(closet.index() - 1);
+        assert_eq!(engine.pipes().len(), 1);
+        assert_eq!((engine.pipes()[0].a, engine.pipes()[0].b), (merged, root));
+        assert_eq!(engine.leaks()[0].container, closet);
+        assert_eq!(engine.last_bursts()[0].container, closet);
+        assert_eq!(engine.reactions_for(merged).len(), 1);
+        assert_eq!(engine.containers[root.index()].children, vec![merged]);
+        assert_eq!(engine.containers[merged.index()].children, vec![closet]);
+        engine.tick();
+    }
+
+    #[test]
+    fn containers_are_found_by_unique_name() {
+        let (mut engine, [root, tank, room, closet]) = merge_split_fixture();
+        let volume = Volume::new(5);
+        let (gas, fluid, solid) = (Gas::zero(), Fluid::zero(), Solid::zero());
+        engine.set_name(root, "atmosphere").unwrap();
+        let habitat = engine
+            .add_named_container(root, "habitat", volume, gas, fluid, solid)
+            .unwrap();
+        engine.set_name(tank, "tank").unwrap();
+        engine.set_name(closet, "closet").unwrap();
+        assert_eq!(engine.container_by_name("atmosphere"), Some(root));
+        assert_eq!(engine.container_by_name("habitat"), Some(habitat));
+        assert_eq!(engine.container(habitat).label(), Some("habitat"));
+        // Relabeling only changes the display text.
+        engine.set_label(habitat, "living quarters");
+        assert_eq!(engine.container_by_name("habitat"), Some(habitat));
+        assert_eq!(engine.find_by_label("habitat"), None);
+        assert_eq!(engine.container_by_name("greenhouse"), None);
+
+        // Taken names are refused without adding anything, but renaming in place is fine.
+        let count = engine.stats().container_count;
+        let taken = engine.add_named_container(root, "tank", volume, gas, fluid, solid);
+        assert_eq!(
+            taken.unwrap_err().to_string(),
+            "name \"tank\" already belongs to container 1"
+        );
+        assert_eq!(engine.stats().container_count, count);
+        assert!(engine.set_name(room, "habitat").is_err());
+        engine.set_name(tank, "tank").unwrap();
+        engine.set_name(tank, "water").unwrap();
+        assert_eq!(engine.container_by_name("tank"), None);
+
+        // Merging the tank into the unnamed room carries its name over, and the names of
+        // containers above the tank follow their shifted ids.
+        let merged = engine.merge_containers(room, tank);
+        assert_eq!(engine.container_by_name("water"), Some(merged));
+        assert_eq!(
+            engine.container_by_name("closet"),
+            Some(ContainerId(closet.index() - 1))
+        );
+        assert_eq!(
+            engine.container_by_name("habitat"),
+            Some(ContainerId(habitat.index() - 1))
+        );
+    }
+
+    #[test]
+    fn simultaneous_pipes_share_a_nearly_empty_source() {
+        let o2 = |o2| Gas {
+            o2,
+            co2: 0,
+            co: 0,
+            h2o: 0,
+        };
+        let drained = |scheduling: Option<PipeScheduling>| {
+            let mut engine = Engine::new(Volume::new(1), o2(10), Fluid::zero(), Solid::zero());
+            if let Some(scheduling) = scheduling {
+                engine.set_pipe_scheduling(scheduling);
+            }
+            let source = engine.root();
+            let mut room = || {
+                engine.add_container(
+                    source,
+                    Volume::new(1000),
+                    o2(0),
+                    Fluid::zero(),
+                    Solid::zero(),
+                )
+            };
+            let (first, second) = (room(), room());
+            engine.add_pipe(source, first, o2(100));
+            engine.add_pipe(source, second, o2(100));
+            engine.tick();
+            let flows: Vec<i64> = engine
+                .last_pipe_flows()
+                .iter()
+                .map(|flow| flow.o2)
+                .collect();
+            assert_eq!(
+                flows,
+                [
+                    engine.container(first).gas.o2,
+                    engine.container(second).gas.o2
+                ]
+            );
+            assert_eq!(engine.stats().total_gas, o2(10));
+            flows
+        };
+
+        // Each pipe alone would move 9 of the 10 moles into its big, empty room.
+        assert_eq!(drained(Some(PipeScheduling::Sequential)), [9, 0]);
+        assert_eq!(drained(Some(PipeScheduling::Simultaneous)), [5, 5]);
+        // Sharing is opt-in.
+        assert_eq!(drained(None), [9, 0]);
+    }
+
+    #[test]
+    fn small_container_between_two_equal_tanks_never_bursts() {
+        let o2 = |o2| Gas {
+            o2,
+            co2: 0,
+            co: 0,
+            h2o: 0,
+        };
+        let bursts = |scheduling: Option<PipeScheduling>| {
+            let mut engine = Engine::new(Volume::new(1), Gas::zero(), Fluid::zero(), Solid::zero());
+            if let Some(scheduling) = scheduling {
+                engine.set_pipe_scheduling(scheduling);
+            }
+            let root = engine.root();
+            let mut add = |volume, gas| {
+                engine.add_container(root, Volume::new(volume), gas, Fluid::zero(), Solid::zero())
+            };
+            let tank = o2(100_000);
+            let (left, hub, right) = (add(1000, tank), add(1, o2(0)), add(1000, tank));
+            engine.set_max_pressure(hub, Some(150));
+            engine.add_pipe(left, hub, o2(1000));
+            engine.add_pipe(right, hub, o2(1000));
+            (0..20)
+                .filter(|_| {
+                    engine.tick();
+                    !engine.last_bursts().is_empty()
+                })
+                .count()
+        };
+
+        // Sequential pipes stop at the first tank's pressure of 99. Simultaneous pipes both
+        // fill the hub from the same empty start, so it overshoots to 198 and bursts.
+        assert_eq!(bursts(None), 0);
+        assert_eq!(bursts(Some(PipeScheduling::Sequential)), 0);
+        assert!(bursts(Some(PipeScheduling::Simultaneous)) > 0);
+    }
+
+    #[test]
+    fn closing_the_vent_valve_keeps_co_in_the_habitat() {
+        // The engine_cli habitat: MOXIE makes CO, and a CO-only pipe vents it outside.
+        let run = |vent_open| {
+            let co_only = Gas {
+                o2: 0,
+                co2: 0,
+                co: 2,
+                h2o: 0,
+            };
+            let mut engine = Engine::new(
+                Volume::new(10_000),
+                Gas::zero(),
+                Fluid::zero(),
+                Solid::zero(),
+            );
+            let root = engine.root();
+            let habitat = engine.add_container(
+                root,
+                Volume::new(100),
+                Gas {
+                    o2: 20_200,
+                    co2: 80_800,
+                    co: 0,
+                    h2o: 0,
+                },
+                Fluid::zero(),
+                Solid::zero(),
+            );
+            let vent = engine.add_pipe(habitat, root, co_only);
+            assert_eq!(engine.pipe(vent).b, root);
+            add_moxie(&mut engine, habitat, 2);
+            engine.set_pipe_open(vent, vent_open);
+            for _ in 0..10 {
+                engine.tick();
+            }
+            assert_eq!(engine.pipe(vent).open, vent_open);
+            let flows = engine.last_pipe_flows()[vent.index()];
+            (
+                engine.container(habitat).gas.co,
+                engine.container(root).gas.co,
+                flows.co,
+            )
+        };
+
+        assert_eq!(run(false), (20, 0, 0));
+        let (habitat_co, vented_co, last_flow) = run(true);
+        assert!(habitat_co < 20 && last_flow > 0);
+        assert_eq!(habitat_co + vented_co, 20);
+
+        // The implicit solver skips closed pipes too.
+        let mut engine = Engine::new(Volume::new(10), Gas::zero(), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        let tank = engine.add_container(
+            root,
+            Volume::new(10),
+            Gas {
+                o2: 100,
+                co2: 0,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        let pipe = engine.add_pipe(
+            tank,
+            root,
+            Gas {
+                o2: 5,
+                co2: 5,
+                co: 5,
+                h2o: 5,
+            },
+        );
+        engine.set_pipe_open(pipe, false);
+        engine.tick_implicit();
+        assert_eq!(engine.container(tank).gas.o2, 100);
+    }
+
+    #[test]
+    fn overpumped_habitat_bursts_into_the_root() {
+        let gas = |o2| Gas {
+            o2,
+            co2: 0,
+            co: 0,
+            h2o: 0,
+        };
+        let mut engine = Engine::new(
+            Volume::new(1000),
+            gas(100_000),
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        let root = engine.root();
+        let habitat = engine.add_container(
+            root,
+            Volume::new(10),
+            gas(100),
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        engine.set_max_pressure(habitat, Some(30));
+        engine.add_pump(root, habitat, gas(80));
+        let atoms = engine.atom_inventory();
+
+        // 100 + 80 moles is a pressure of 18, then 26, then 34 which is over the limit.
+        engine.tick();
+        engine.tick();
+        assert_eq!(engine.last_bursts(), []);
+        engine.tick();
+        assert_eq!(
+            engine.last_bursts(),
+            [BurstEvent {
+                container: habitat,
+                pressure: 34,
+            }]
+        );
+        assert_eq!(engine.container(habitat).pressure(), 17);
+        assert_eq!(engine.check_conserved(&atoms), Ok(()));
+        engine.tick();
+        assert_eq!(engine.last_bursts(), []);
+    }
+
+    #[test]
+    fn sealed_child_slowly_equalizes_with_its_parent() {
+        let gas = |o2| Gas {
+            o2,
+            co2: 0,
+            co: 0,
+            h2o: 0,
+        };
+        let mut engine = Engine::new(Volume::new(10_000), gas(0), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        let child = engine.add_container(
+            root,
+            Volume::new(100),
+            gas(2_000_000),
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        let grandchild = engine.add_container(
+            child,
+            Volume::new(100),
+            gas(0),
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        let atoms = engine.atom_inventory();
+
+        // Without a rate the tree stays sealed.
+        engine.tick();
+        assert_eq!(engine.container(child).gas().o2, 2_000_000);
+
+        engine.set_diffusion_rate(50);
+        engine.tick();
+        // Five percent of the 1_980_198 moles that would equalize the child with the root.
+        assert_eq!(engine.container(root).gas().o2, 99_009);
+        assert!(engine.container(grandchild).gas().o2 > 0);
+
+        let mut last = engine.container(child).pressure();
+        for _ in 0..2_000 {
+            engine.tick();
+            let pressure = engine.container(child).pressure();
+            assert!(pressure <= last);
+            last = pressure;
+        }
+        assert_eq!(engine.check_conserved(&atoms), Ok(()));
+        // All three hold 2_000_000 / 10_200 moles per unit of volume, give or take a few moles.
+        for id in [root, child, grandchild] {
+            assert_eq!(engine.container(id).pressure(), 196);
+        }
+    }
+
+    #[test]
+    fn splitting_conserves_totals_and_keeps_attachments() {
+        let (mut engine, [_, tank, room, _]) = merge_split_fixture();
+        let atoms = engine.atom_inventory();
+
+        let part = engine.split_container(tank, 1, 3);
+        assert_eq!(engine.check_conserved(&atoms), Ok(()));
+        assert_eq!(engine.container(part).volume(), Volume::new(3));
+        assert_eq!(engine.container(tank).volume(), Volume::new(7));
+        assert_eq!(
+            engine.container(part).gas().o2 + engine.container(tank).gas().o2,
+            90
+        );
+        assert_eq!(engine.container(part).fluid(), Fluid { h2o: 2 });
+        assert_eq!(engine.container(tank).solid().ch2o, 2);
+        assert!(engine.containers[engine.root().index()]
+            .children
+            .contains(&part));
+        assert_eq!((engine.pipes()[0].a, engine.pipes()[0].b), (tank, room));
+
+        // Merging the part back restores the original tank.
+        let (original, _) = merge_split_fixture();
+        let tank = engine.merge_containers(tank, part);
+        assert_eq!(engine.container(tank), original.container(tank));
+    }
+
+    #[test]
+    fn last_pipe_flows_record_clamped_amounts_per_pipe() {
+        let gas = |o2, co2| Gas {
+            o2,
+            co2,
+            co: 0,
+            h2o: 0,
+        };
+        let mut engine = Engine::new(Volume::new(10), gas(100, 0), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        let tank = engine.add_container(
+            root,
+            Volume::new(10),
+            gas(0, 40),
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        engine.add_pipe(tank, root, gas(30, 30));
+        assert_eq!(engine.last_pipe_flows(), &[]);
+        let mut implicit = engine.clone();
+
+        // Equalizing would move 50 O2 from the root, but the pipe caps it at 30.
+        engine.tick();
+        assert_eq!(engine.last_pipe_flows(), &[gas(-30, 20)]);
+        assert_eq!(engine.container(tank).gas(), gas(30, 20));
+
+        implicit.tick_implicit();
+        let flow = implicit.last_pipe_flows()[0];
+        let moved = implicit.container(root).gas() + gas(-100, 0);
+        assert!(flow.o2 < 0 && flow.co2 > 0, "{flow:?}");
+        assert!((flow.o2 - moved.o2).abs() <= 1, "{flow:?} vs {moved:?}");
+        assert!((flow.co2 - moved.co2).abs() <= 1, "{flow:?} vs {moved:?}");
+    }
+
+    #[test]
+    fn ticking_approaches_the_predicted_equilibrium() {
+        let rate = Gas {
+            o2: 50,
+            co2: 50,
+            co: 50,
+            h2o: 50,
+        };
+        let mut engine = Engine::new(
+            Volume::new(30),
+            Gas {
+                o2: 600,
+                co2: 300,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        let root = engine.root();
+        let (fluid, solid) = (Fluid::zero(), Solid::zero());
+        let tank = engine.add_container(root, Volume::new(10), Gas::zero(), fluid, solid);
+        engine.add_pipe(root, tank, rate);
+        let expected = engine.predict_equilibrium(root, tank);
+        assert_eq!(expected, 22);
+        for _ in 0..50 {
+            engine.tick();
+        }
+        for id in [root, tank] {
+            let container = engine.container(id);
+            let pressure = container.gas.pressure_exact(container.volume);
+            assert!((pressure - expected).abs() <= 1, "{pressure} vs {expected}");
+        }
+
+        // A tiny tank next to the atmosphere barely moves the atmosphere's pressure.
+        let atmosphere = Volume::new(93_000_000_000_000);
+        let mut engine = Engine::new(
+            atmosphere,
+            Gas {
+                o2: 0,
+                co2: 600 * atmosphere.value(),
+                co: 0,
+                h2o: 0,
+            },
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        let root = engine.root();
+        let tank = engine.add_container(root, Volume::new(10), Gas::zero(), fluid, solid);
+        // Just under 600, which floors to 599 like every other integer pressure.
+        assert_eq!(engine.predict_equilibrium(root, tank), 599);
+        assert_eq!(engine.predict_equilibrium(root, root), 600);
+    }
+
+    #[test]
+    fn find_by_label_returns_first_match() {
+        let mut engine = Engine::new(Volume::new(10), Gas::zero(), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        engine.set_label(root, "atmosphere");
+        let (volume, gas) = (Volume::new(1), Gas::zero());
+        let (fluid, solid) = (Fluid::zero(), Solid::zero());
+        let first = engine.add_labeled_container(root, "tank", volume, gas, fluid, solid);
+        engine.add_labeled_container(root, "tank", volume, gas, fluid, solid);
+
+        assert_eq!(engine.find_by_label("atmosphere"), Some(root));
+        assert_eq!(engine.find_by_label("tank"), Some(first));
+        assert_eq!(engine.find_by_label("habitat"), None);
+        assert_eq!(engine.container(first).label(), Some("tank"));
+    }
+
+    #[test]
+    fn history_records_pressures_once_enabled() {
+        let mut engine = replay_engine();
+        engine.tick();
+        assert!(engine.history().is_empty());
+
+        engine.enable_history();
+        engine.tick();
+        engine.tick_implicit();
+        let history = engine.history().to_vec();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].len(), 3);
+        let habitat = ContainerId(1);
+        assert_eq!(
+            history[1][habitat.index()],
+            engine.container(habitat).pressure()
+        );
+
+        engine.clear_history();
+        assert!(engine.history().is_empty());
+        engine.tick();
+        assert_eq!(engine.history().len(), 1);
+    }
+
+    #[test]
+    fn pipes_for_reports_the_attached_end() {
+        let engine = replay_engine();
+        let (root, habitat, greenhouse) = (engine.root(), ContainerId(1), ContainerId(2));
+        let ends = |id| {
+            engine
+                .pipes_for(id)
+                .map(|(end, pipe)| (end, pipe.a, pipe.b))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            ends(habitat),
+            [
+                (PipeEnd::A, habitat, root),
+                (PipeEnd::A, habitat, greenhouse)
+            ]
+        );
+        assert_eq!(ends(greenhouse), [(PipeEnd::B, habitat, greenhouse)]);
+        assert_eq!(ends(root), [(PipeEnd::B, habitat, root)]);
+    }
+
+    #[test]
+    fn required_species_gate_reactions_without_being_consumed() {
+        let mut engine = Engine::new(Volume::new(1000), Gas::zero(), Fluid::zero(), Solid::zero());
+        let habitat = engine.add_container(
+            engine.root(),
+            Volume::new(100),
+            Gas {
+                o2: 4_000,
+                co2: 100_000,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid { h2o: 100_000 },
+            Solid {
+                ch2o: 10_000,
+                ice: 0,
+            },
+        );
+        // Plants that the controller only lights while O2 is under 50, for a crew breathing
+        // half of what they make.
+        let plants = engine.add_reaction_with_requirement(
+            habitat,
+            Gas {
+                o2: 100,
+                co2: -100,
+                co: 0,
+                h2o: 0,
+            },
+            Fluid { h2o: -100 },
+            Solid { ch2o: 100, ice: 0 },
+            ReactionRequirement::PressureBelow {
+                species: Species::O2,
+                pressure: 50,
+            },
+        );
+        assert_eq!(plants.index(), 0);
+        add_human(&mut engine, habitat, 50);
+        let o2_pressure = |engine: &Engine| engine.container(habitat).gas().o2 / 100;
+
+        for _ in 0..20 {
+            engine.tick();
+        }
+        assert_eq!(o2_pressure(&engine), 50);
+        for _ in 0..100 {
+            engine.tick();
+            assert!((49..=50).contains(&o2_pressure(&engine)));
+        }
+
+        // A catalyst must be present but is left as it was.
+        let air = Gas {
+            o2: 0,
+            co2: 100,
+            co: 0,
+            h2o: 100,
+        };
+        let mut lab = Engine::new(Volume::new(10), air, Fluid::zero(), Solid::zero());
+        let root = lab.root();
+        let scrubber = Gas {
+            o2: 10,
+            co2: -10,
+            co: 0,
+            h2o: -10,
+        };
+        let co_catalyst = ReactionRequirement::AtLeast(Gas {
+            co: 10,
+            ..Gas::zero()
+        });
+        let ch2o = Solid { ch2o: 10, ice: 0 };
+        lab.add_reaction_with_requirement(root, scrubber, Fluid::zero(), ch2o, co_catalyst);
+        lab.tick();
+        assert_eq!(lab.container(root).gas(), air);
+        lab.container_mut(root).gas.co = 10;
+        lab.tick();
+        let scrubbed = Gas {
+            co: 10,
+            ..air + scrubber
+        };
+        assert_eq!(lab.container(root).gas(), scrubbed);
+    }
+
+    #[test]
+    fn lit_photosynthesis_scales_with_light_and_co2() {
+        let co2_fixed = |co2, light_level| {
+            let mut engine =
+                Engine::new(Volume::new(10), Gas::zero(), Fluid::zero(), Solid::zero());
+            let root = engine.root();
+            let greenhouse = engine.add_container(
+                root,
+                Volume::new(10),
+                Gas {
+                    o2: 0,
+                    co2,
+                    co: 0,
+                    h2o: 0,
+                },
+                Fluid { h2o: 100 },
+                Solid::zero(),
+            );
+            add_photosynthesis_lit(&mut engine, greenhouse, 8, 5);
+            engine.set_light_level(greenhouse, light_level);
+            let before = engine.atom_inventory();
+            engine.tick();
+            assert_eq!(engine.atom_inventory(), before);
+            engine.container(greenhouse).solid.ch2o
+        };
+
+        // 50 moles of CO2 in 10 volume is the half-rate pressure of 5.
+        assert_eq!(co2_fixed(50, FULL_LIGHT), 4);
+        assert_eq!(co2_fixed(50, FULL_LIGHT / 2), 2);
+        assert_eq!(co2_fixed(50, 0), 0);
+        assert_eq!(co2_fixed(1000, FULL_LIGHT), 7);
+    }
+
+    #[test]
+    fn sunlight_follows_the_sun_elevation() {
+        let mut engine = Engine::new(Volume::new(10), Gas::zero(), Fluid::zero(), Solid::zero());
+        let root = engine.root();
+        let sun = |elevation| SolarAngles {
+            elevation,
+            azimuth: 0.0,
+        };
+        engine.set_sunlight(root, sun(std::f32::consts::FRAC_PI_2));
+        assert_eq!(engine.container(root).light_level(), FULL_LIGHT);
+        engine.set_sunlight(root, sun(std::f32::consts::FRAC_PI_6));
+        assert_eq!(engine.container(root).light_level(), FULL_LIGHT / 2);
+        engine.set_sunlight(root, sun(-0.1));
+        assert_eq!(engine.container(root).light_level(), 0);
+    }
+
+    #[test]
+    fn simultaneous_reactions_share_scarce_o2_fairly() {
+        let o2_left = |scheduling, swap: bool| {
+            let mut engine =
+                Engine::new(Volume::new(10), Gas::zero(), Fluid::zero(), Solid::zero());
+            let root = engine.root();
+            let cabin = engine.add_container(
+                root,
+                Volume::new(10),
+                Gas {
+                    o2: 10,
+                    co2: 0,
+                    co: 0,
+                    h2o: 0,
+                },
+                Fluid::zero(),
+                Solid { ch2o: 100, ice: 0 },
+            );
+            engine.set_reaction_scheduling(scheduling);
+            let (first, second) = if swap { (9, 6) } else { (6, 9) };
+            add_human(&mut engine, cabin, first);
+            add_human(&mut engine, cabin, second);
+            let before = engine.atom_inventory();
+            engine.tick();
+            assert_eq!(engine.atom_inventory(), before);
+            (
+                engine.container(cabin).gas.o2,
+                engine.container(cabin).solid.ch2o,
+            )
+        };
+
+        // Sequentially, the first human to be added breathes and the other one starves.
+        assert_eq!(o2_left(ReactionScheduling::Sequential, false), (4, 94));
+        assert_eq!(o2_left(ReactionScheduling::Sequential, true), (1, 91));
+        // Demand is 15 for 10 available, so both run at 2/3 of their rate.
+        let fair = o2_left(ReactionScheduling::Simultaneous, false);
+        assert_eq!(fair, (0, 90));
+        assert_eq!(o2_left(ReactionScheduling::Simultaneous, true), fair);
+    }
+
+    #[test]
+    fn parallel_tick_matches_serial_tick() {
+        for scheduling in [
+            ReactionScheduling::Sequential,
+            ReactionScheduling::Simultaneous,
+        ] {
+            let mut engine = Engine::new(
+                Volume::new(100_000),
+                Gas::zero(),
+                Fluid::zero(),
+                Solid::zero(),
+            );
+            engine.set_reaction_scheduling(scheduling);
+            let root = engine.root();
+            let habitats: Vec<ContainerId> = (0..300)
+                .map(|index| {
+                    let habitat = engine.add_container(
+                        root,
+                        Volume::new(100),
+                        Gas {
+                            o2: 50 + index % 13 * 20,
+                            co2: 100 + index % 7 * 30,
+                            co: 0,
+                            h2o: 0,
+                        },
+                        Fluid { h2o: 40 },
+                        Solid {
+                            ch2o: index % 11 * 5,
+                            ice: 0,
+                        },
+                    );
+                    engine.set_light_level(habitat, FULL_LIGHT / 2);
+                    habitat
+                })
+                .collect();
+            // Clusters of two habitats share a pipe, and reactions are added interleaved so
+            // each container's reactions are spread through the engine's list.
+            for pair in habitats.chunks(2) {
+                let flow_rate = Gas {
+                    o2: 3,
+                    co2: 3,
+                    co: 3,
+                    h2o: 3,
+                };
+                engine.add_pipe(pair[0], pair[1], flow_rate);
+            }
+            for round in 0..3 {
+                for (index, &habitat) in habitats.iter().enumerate() {
+                    match (index + round) % 3 {
+                        0 => add_human(&mut engine, habitat, 3 + index as i64 % 4),
+                        1 => add_photosynthesis_lit(&mut engine, habitat, 4, 20),
+                        _ => add_moxie(&mut engine, habitat, 2),
+                    }
+                }
+            }
+
+            let mut serial = engine.clone();
+            let mut parallel = engine;
+            for _ in 0..20 {
+                serial.tick();
+                parallel.tick_parallel();
+                assert_eq!(parallel, serial, "{scheduling:?}");
+            }
+        }
+    }
 }
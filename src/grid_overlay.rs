@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+use bevy::render::mesh::Mesh;
+use bevy::render::render_resource::PrimitiveTopology;
+use std::collections::HashSet;
+
+use crate::isometric::IsoCameraTag;
+use crate::{visible_map_chunks, TileMap, CHUNK_SIZE, TILE_SIZE};
+
+// G toggles lines along every tile edge in view, for checking tile boundaries and picking.
+const TOGGLE_KEY: KeyCode = KeyCode::G;
+const GRID_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.35);
+// Just above the tiles, so the lines don't z-fight with the ground.
+const GRID_LIFT: f32 = 0.02;
+
+#[derive(Resource, Default)]
+pub struct GridOverlay {
+    enabled: bool,
+}
+
+// Grid lines for one terrain chunk.
+#[derive(Component)]
+pub struct GridLines {
+    coord: IVec2,
+}
+
+// Keeps one line mesh per visible chunk while the overlay is on. Lines are rasterized one pixel
+// wide whatever the zoom, so the grid stays readable zoomed out and never hides the tiles.
+#[allow(clippy::too_many_arguments)] // Bevy system; each argument is a system param.
+pub fn update_grid_overlay(
+    mut commands: Commands,
+    mut overlay: ResMut<GridOverlay>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut material: Local<Option<Handle<StandardMaterial>>>,
+    keys: Res<Input<KeyCode>>,
+    map: Res<TileMap>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<IsoCameraTag>>,
+    lines: Query<(Entity, &GridLines)>,
+) {
+    if keys.just_pressed(TOGGLE_KEY) {
+        overlay.enabled = !overlay.enabled;
+    }
+    let visible: HashSet<IVec2> = match (overlay.enabled, camera_query.get_single()) {
+        (true, Ok((camera, camera_transform))) => {
+            visible_map_chunks(&map, camera, camera_transform)
+                .into_iter()
+                .map(|(x, y)| IVec2::new(x as i32, y as i32))
+                .collect()
+        }
+        _ => HashSet::new(),
+    };
+
+    let mut drawn = HashSet::new();
+    for (entity, lines) in &lines {
+        if visible.contains(&lines.coord) {
+            drawn.insert(lines.coord);
+        } else {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    let half_size = Vec2::new(map.width as f32, map.height as f32) * TILE_SIZE * 0.5;
+    for &coord in visible.difference(&drawn) {
+        let material = material
+            .get_or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: GRID_COLOR,
+                    unlit: true,
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                })
+            })
+            .clone();
+        let positions = chunk_grid_lines(coord, half_size);
+        let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_NORMAL,
+            vec![[0.0f32, 1.0, 0.0]; positions.len()],
+        );
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(mesh),
+                material,
+                ..default()
+            },
+            GridLines { coord },
+        ));
+    }
+}
+
+// Endpoint pairs for every tile edge in a chunk, laid out like `build_chunk_mesh` lays tiles.
+fn chunk_grid_lines(coord: IVec2, half_size: Vec2) -> Vec<[f32; 3]> {
+    let origin = coord.as_vec2() * CHUNK_SIZE as f32 * TILE_SIZE - half_size;
+    let length = CHUNK_SIZE as f32 * TILE_SIZE;
+    let mut positions = Vec::with_capacity((CHUNK_SIZE + 1) * 4);
+    for line in 0..=CHUNK_SIZE {
+        let offset = line as f32 * TILE_SIZE;
+        let (x, z) = (origin.x + offset, origin.y + offset);
+        positions.push([origin.x, GRID_LIFT, z]);
+        positions.push([origin.x + length, GRID_LIFT, z]);
+        positions.push([x, GRID_LIFT, origin.y]);
+        positions.push([x, GRID_LIFT, origin.y + length]);
+    }
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_lines_follow_the_chunks_tile_edges() {
+        let half_size = Vec2::splat(64.0) * TILE_SIZE * 0.5;
+        let positions = chunk_grid_lines(IVec2::new(1, 0), half_size);
+        assert_eq!(positions.len(), (CHUNK_SIZE + 1) * 4);
+
+        // The chunk starts one chunk in from the map's west edge and on its north edge.
+        let chunk = CHUNK_SIZE as f32 * TILE_SIZE;
+        let (west, north) = (chunk - half_size.x, -half_size.y);
+        assert_eq!(positions[0], [west, GRID_LIFT, north]);
+        assert_eq!(positions[1], [west + chunk, GRID_LIFT, north]);
+        assert_eq!(positions[6], [west + TILE_SIZE, GRID_LIFT, north]);
+        assert_eq!(positions[7], [west + TILE_SIZE, GRID_LIFT, north + chunk]);
+    }
+}
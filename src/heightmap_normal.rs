@@ -3,50 +3,213 @@ use bevy::render::render_resource::{
     AddressMode, Extent3d, FilterMode, SamplerDescriptor, TextureDimension, TextureFormat,
 };
 use bevy::render::texture::{ImageSampler, TextureFormatPixelInfo};
+use std::f32::consts::FRAC_PI_2;
 
+/// Component layout used when encoding normals into the output texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalSpace {
+    /// Object space with +Y up and +Z along +V: flat ground encodes as (0.5, 1.0, 0.5).
+    #[allow(dead_code)] // The terrain shader reads tangent space; only the tests bake this.
+    ObjectYUp,
+    /// Tangent space (T, B, N) with +Z out of the surface and +Y along +V (OpenGL-style):
+    /// flat ground encodes as (0.5, 0.5, 1.0). This is what `StandardMaterial` expects.
+    TangentZUp,
+}
+
+// Which part of each heightmap pixel encodes elevation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[allow(dead_code)] // The bundled heightmap is grayscale, so the game only reads `Luma`.
+pub enum HeightChannel {
+    // Equal-weight average of red, green and blue.
+    #[default]
+    Luma,
+    Red,
+    Green,
+    Blue,
+}
+
+// Heights in world units, row-major with `width` samples per row. Reading a big heightmap is
+// the slow part, so extract it once and pass the same field to each map builder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightfield {
+    heights: Vec<f32>,
+    width: usize,
+    height: usize,
+}
+
+impl Heightfield {
+    pub fn new(heights: Vec<f32>, width: usize, height: usize) -> Self {
+        assert_eq!(
+            heights.len(),
+            width * height,
+            "heights do not match the given dimensions"
+        );
+        Self {
+            heights,
+            width,
+            height,
+        }
+    }
+
+    // Heights in [0, bump_scale] from one channel, or the luma, of an 8- or 16-bit, one- or
+    // four-channel image. Single-channel images, and gray-alpha ones, read the gray value
+    // whichever channel is asked for. sRGB-tagged images (8-bit PNGs load that way) are read as
+    // raw values, like their linear twins. 16-bit PNGs load as the `Uint` formats, which are
+    // read like their `Unorm` twins.
+    pub fn from_image(image: &Image, bump_scale: f32, channel_choice: HeightChannel) -> Self {
+        let width = image.texture_descriptor.size.width as usize;
+        let height = image.texture_descriptor.size.height as usize;
+        let format = image.texture_descriptor.format;
+        let (channels, bytes_per_channel) = match format {
+            TextureFormat::R8Unorm => (1, 1),
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => (4, 1),
+            TextureFormat::R16Unorm | TextureFormat::R16Uint => (1, 2),
+            TextureFormat::Rg16Uint => (2, 2),
+            TextureFormat::Rgba16Unorm | TextureFormat::Rgba16Uint => (4, 2),
+            _ => panic!(
+                "unsupported heightmap format {format:?}: expected R8Unorm, Rgba8Unorm, \
+                 Rgba8UnormSrgb, R16Unorm, R16Uint, Rg16Uint, Rgba16Unorm or Rgba16Uint"
+            ),
+        };
+        let pixel_stride = channels * bytes_per_channel;
+        let heightmap_data = &image.data;
+        assert!(
+            heightmap_data.len() >= width * height * pixel_stride,
+            "heightmap data does not match image dimensions"
+        );
+
+        let channel = |offset: usize| {
+            if bytes_per_channel == 1 {
+                heightmap_data[offset] as f32 / 255.0
+            } else {
+                let bytes = [heightmap_data[offset], heightmap_data[offset + 1]];
+                u16::from_le_bytes(bytes) as f32 / 65535.0
+            }
+        };
+        let mut heights = Vec::with_capacity(width * height);
+        for index in 0..width * height {
+            let offset = index * pixel_stride;
+            let value = match (channels, channel_choice) {
+                (1 | 2, _) => channel(offset),
+                (_, HeightChannel::Luma) => {
+                    (0..3)
+                        .map(|c| channel(offset + c * bytes_per_channel))
+                        .sum::<f32>()
+                        / 3.0
+                }
+                (_, HeightChannel::Red) => channel(offset),
+                (_, HeightChannel::Green) => channel(offset + bytes_per_channel),
+                (_, HeightChannel::Blue) => channel(offset + 2 * bytes_per_channel),
+            };
+            heights.push(value * bump_scale);
+        }
+
+        Self::new(heights, width, height)
+    }
+
+    pub fn heights(&self) -> &[f32] {
+        &self.heights
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    // Bilinear height at a fractional texel position, clamped to the field's edges.
+    pub fn sample(&self, x: f32, y: f32) -> f32 {
+        let x = x.clamp(0.0, (self.width - 1) as f32);
+        let y = y.clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+        let at = |x: usize, y: usize| self.heights[y * self.width + x];
+        let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * tx;
+        let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * tx;
+        top + (bottom - top) * ty
+    }
+
+    // Averages each `factor` x `factor` block into one sample, for coarse passes over a big map.
+    pub fn downsample(&self, factor: usize) -> Self {
+        assert!(
+            factor > 0 && self.width.is_multiple_of(factor) && self.height.is_multiple_of(factor),
+            "downsample factor must divide the heightfield size"
+        );
+        let (width, height) = (self.width / factor, self.height / factor);
+        let mut heights = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = 0.0;
+                for row in y * factor..(y + 1) * factor {
+                    let start = row * self.width + x * factor;
+                    sum += self.heights[start..start + factor].iter().sum::<f32>();
+                }
+                heights.push(sum / (factor * factor) as f32);
+            }
+        }
+        Self::new(heights, width, height)
+    }
+
+    fn gradient(&self, x: usize, y: usize, world_scale: f32) -> (f32, f32) {
+        heightmap_gradient(&self.heights, self.width, self.height, x, y, world_scale)
+    }
+}
+
+// 16 bits per channel keeps gentle slopes from banding; 8-bit output is opt-in.
+pub const DEFAULT_NORMAL_FORMAT: TextureFormat = TextureFormat::Rgba16Unorm;
+
+// `build_normal_map_from_heightfield` in `DEFAULT_NORMAL_FORMAT`.
+pub fn build_normal_map(field: &Heightfield, world_scale: f32, space: NormalSpace) -> Image {
+    build_normal_map_from_heightfield(field, world_scale, DEFAULT_NORMAL_FORMAT, space)
+}
+
+#[allow(dead_code)] // For one-off images; the game builds its `Heightfield` once and reuses it.
 pub fn build_heightmap_normal_map(
     image: &Image,
     bump_scale: f32,
     world_scale: f32,
+    channel: HeightChannel,
+    output_format: TextureFormat,
+    space: NormalSpace,
+) -> Image {
+    let field = Heightfield::from_image(image, bump_scale, channel);
+    build_normal_map_from_heightfield(&field, world_scale, output_format, space)
+}
+
+pub fn build_normal_map_from_heightfield(
+    field: &Heightfield,
+    world_scale: f32,
+    output_format: TextureFormat,
+    space: NormalSpace,
 ) -> Image {
-    let width = image.texture_descriptor.size.width as usize;
-    let height = image.texture_descriptor.size.height as usize;
-    let pixel_stride = image.texture_descriptor.format.pixel_size();
-    let heightmap_data = &image.data;
-    assert!(pixel_stride >= 1, "heightmap texture must be uncompressed");
+    let (width, height) = (field.width, field.height);
     assert!(
-        heightmap_data.len() >= width * height * pixel_stride,
-        "heightmap data does not match image dimensions"
+        matches!(
+            output_format,
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba16Unorm
+        ),
+        "normal map output format must be Rgba8Unorm or Rgba16Unorm"
     );
-
-    let mut heights = Vec::with_capacity(width * height);
+    let mut normal_data = Vec::with_capacity(width * height * output_format.pixel_size());
     for y in 0..height {
         for x in 0..width {
-            let index = (y * width + x) * pixel_stride;
-            let r = heightmap_data[index] as f32 / 255.0;
-            let g = if pixel_stride > 1 {
-                heightmap_data[index + 1] as f32 / 255.0
-            } else {
-                r
+            let normal = heightmap_normal(field.gradient(x, y, world_scale));
+            let normal = match space {
+                NormalSpace::ObjectYUp => Vec3::new(normal.x, normal.z, normal.y),
+                NormalSpace::TangentZUp => normal,
             };
-            let b = if pixel_stride > 2 {
-                heightmap_data[index + 2] as f32 / 255.0
+            if output_format == TextureFormat::Rgba8Unorm {
+                normal_data.extend_from_slice(&[
+                    normal_channel_u8(normal.x),
+                    normal_channel_u8(normal.y),
+                    normal_channel_u8(normal.z),
+                    u8::MAX,
+                ]);
             } else {
-                r
-            };
-            let luma = (r + g + b) / 3.0;
-            heights.push(luma * bump_scale);
-        }
-    }
-
-    let mut normal_data = Vec::with_capacity(width * height * 8);
-    for y in 0..height {
-        for x in 0..width {
-            let normal = heightmap_normal(&heights, width, height, x, y, world_scale);
-            normal_data.extend_from_slice(&normal_channel_u16(normal.x).to_le_bytes());
-            normal_data.extend_from_slice(&normal_channel_u16(normal.y).to_le_bytes());
-            normal_data.extend_from_slice(&normal_channel_u16(normal.z).to_le_bytes());
-            normal_data.extend_from_slice(&u16::MAX.to_le_bytes());
+                normal_data.extend_from_slice(&normal_channel_u16(normal.x).to_le_bytes());
+                normal_data.extend_from_slice(&normal_channel_u16(normal.y).to_le_bytes());
+                normal_data.extend_from_slice(&normal_channel_u16(normal.z).to_le_bytes());
+                normal_data.extend_from_slice(&u16::MAX.to_le_bytes());
+            }
         }
     }
 
@@ -58,7 +221,7 @@ pub fn build_heightmap_normal_map(
         },
         TextureDimension::D2,
         normal_data,
-        TextureFormat::Rgba16Unorm,
+        output_format,
     );
     image.sampler_descriptor = ImageSampler::Descriptor(SamplerDescriptor {
         address_mode_u: AddressMode::Repeat,
@@ -72,18 +235,58 @@ pub fn build_heightmap_normal_map(
     image
 }
 
-fn heightmap_normal(
+// Slope angle per pixel as R8Unorm grayscale: 0 is flat ground, 255 is vertical.
+#[allow(dead_code)] // No slope overlay draws this yet.
+pub fn build_slope_map(
+    image: &Image,
+    bump_scale: f32,
+    world_scale: f32,
+    channel: HeightChannel,
+) -> Image {
+    let field = Heightfield::from_image(image, bump_scale, channel);
+    build_slope_map_from_heightfield(&field, world_scale)
+}
+
+#[allow(dead_code)] // No slope overlay draws this yet.
+pub fn build_slope_map_from_heightfield(field: &Heightfield, world_scale: f32) -> Image {
+    let (width, height) = (field.width, field.height);
+    let mut slope_data = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dz) = field.gradient(x, y, world_scale);
+            let slope = (dx * dx + dz * dz).sqrt().atan();
+            slope_data.push((slope / FRAC_PI_2 * 255.0).round() as u8);
+        }
+    }
+
+    Image::new(
+        Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        slope_data,
+        TextureFormat::R8Unorm,
+    )
+}
+
+fn heightmap_normal((dx, dz): (f32, f32)) -> Vec3 {
+    // Tangent-space normal (T,B,N) with +Y along +V (OpenGL-style).
+    Vec3::new(-dx, -dz, 1.0).normalize()
+}
+
+// Central-difference height slope along +X and +Z, one-sided at the image edges. The whole
+// heightmap is one surface, so pixels beside an interior atlas patch border sample across it
+// and the normals stay continuous; only the outer edge of the image is clamped.
+fn heightmap_gradient(
     heights: &[f32],
     width: usize,
     height: usize,
     x: usize,
     y: usize,
     world_scale: f32,
-) -> Vec3 {
-    if width == 0 || height == 0 {
-        return Vec3::Y;
-    }
-
+) -> (f32, f32) {
     let x0 = x.saturating_sub(1);
     let x1 = (x + 1).min(width - 1);
     let y0 = y.saturating_sub(1);
@@ -104,12 +307,217 @@ fn heightmap_normal(
     } else {
         (h_u - h_d) / ((y1 - y0) as f32 * world_scale)
     };
-
-    // Tangent-space normal (T,B,N) with +Y along +V (OpenGL-style).
-    Vec3::new(-dx, -dz, 1.0).normalize()
+    (dx, dz)
 }
 
 fn normal_channel_u16(value: f32) -> u16 {
     let clamped = value.clamp(-1.0, 1.0);
     ((clamped * 0.5 + 0.5) * 65535.0).round() as u16
 }
+
+fn normal_channel_u8(value: f32) -> u8 {
+    let clamped = value.clamp(-1.0, 1.0);
+    ((clamped * 0.5 + 0.5) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat_heightmap() -> Image {
+        Image::new_fill(
+            Extent3d {
+                width: 4,
+                height: 4,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[128, 128, 128, 255],
+            TextureFormat::Rgba8Unorm,
+        )
+    }
+
+    #[test]
+    fn flat_surface_encodes_z_up_in_tangent_space() {
+        let normal_map = build_heightmap_normal_map(
+            &flat_heightmap(),
+            1.0,
+            1.0,
+            HeightChannel::Luma,
+            TextureFormat::Rgba8Unorm,
+            NormalSpace::TangentZUp,
+        );
+        for pixel in normal_map.data.chunks_exact(4) {
+            assert_eq!(pixel, [128, 128, 255, 255]);
+        }
+    }
+
+    #[test]
+    fn flat_surface_encodes_y_up_in_object_space() {
+        let normal_map = build_heightmap_normal_map(
+            &flat_heightmap(),
+            1.0,
+            1.0,
+            HeightChannel::Luma,
+            TextureFormat::Rgba8Unorm,
+            NormalSpace::ObjectYUp,
+        );
+        for pixel in normal_map.data.chunks_exact(4) {
+            assert_eq!(pixel, [128, 255, 128, 255]);
+        }
+    }
+
+    #[test]
+    fn ramp_heights_tilt_normals_against_the_slope() {
+        let width = 4;
+        let height = 4;
+        let heights: Vec<f32> = (0..width * height).map(|i| (i % width) as f32).collect();
+        let normal_map = build_normal_map_from_heightfield(
+            &Heightfield::new(heights, width, height),
+            1.0,
+            TextureFormat::Rgba8Unorm,
+            NormalSpace::TangentZUp,
+        );
+        // A 45 degree ramp along +X gives the normal (-1, 0, 1) / sqrt(2).
+        let index = (width + 1) * 4;
+        assert_eq!(&normal_map.data[index..index + 4], [37, 128, 218, 255]);
+    }
+
+    #[test]
+    fn default_normal_map_is_16_bit() {
+        let field = Heightfield::new(vec![0.0; 16], 4, 4);
+        let normal_map = build_normal_map(&field, 1.0, NormalSpace::TangentZUp);
+        assert_eq!(
+            normal_map.texture_descriptor.format,
+            TextureFormat::Rgba16Unorm
+        );
+        assert_eq!(normal_map.data.len(), 16 * 8);
+    }
+
+    #[test]
+    fn gradients_sample_across_interior_patch_borders() {
+        // Two 2-pixel patches side by side on a parabola; the border runs between x=1 and x=2.
+        let heights: Vec<f32> = (0..4).map(|x| (x * x) as f32).collect();
+        let slope_at = |x| heightmap_gradient(&heights, 4, 1, x, 0, 1.0).0;
+        // Both border pixels use their neighbour in the other patch.
+        assert_eq!(slope_at(1), (4.0 - 0.0) / 2.0);
+        assert_eq!(slope_at(2), (9.0 - 1.0) / 2.0);
+        // Only the image's outer edge falls back to a one-sided difference.
+        assert_eq!(slope_at(3), 9.0 - 4.0);
+    }
+
+    #[test]
+    fn one_heightfield_feeds_both_map_builders() {
+        let mut heightmap = flat_heightmap();
+        for (index, pixel) in heightmap.data.chunks_exact_mut(4).enumerate() {
+            let value = (index % 4) as u8 * 10;
+            pixel.copy_from_slice(&[value, value, value, 255]);
+        }
+        let field = Heightfield::from_image(&heightmap, 25.5, HeightChannel::Luma);
+        let format = TextureFormat::Rgba8Unorm;
+        let space = NormalSpace::TangentZUp;
+        assert_eq!(
+            build_normal_map_from_heightfield(&field, 1.0, format, space).data,
+            build_heightmap_normal_map(&heightmap, 25.5, 1.0, HeightChannel::Luma, format, space)
+                .data
+        );
+        assert_eq!(
+            build_slope_map_from_heightfield(&field, 1.0).data,
+            build_slope_map(&heightmap, 25.5, 1.0, HeightChannel::Luma).data
+        );
+    }
+
+    #[test]
+    fn heightfield_samples_bilinearly_and_downsamples_by_averaging() {
+        let field = Heightfield::new(vec![0.0, 2.0, 4.0, 6.0, 8.0, 10.0, 12.0, 14.0], 4, 2);
+        assert_eq!(field.sample(1.5, 0.0), 3.0);
+        assert_eq!(field.sample(0.5, 0.5), 5.0);
+        // Positions off the field clamp to its edge.
+        assert_eq!(field.sample(-1.0, 5.0), 8.0);
+
+        let coarse = field.downsample(2);
+        assert_eq!(coarse.size(), (2, 1));
+        assert_eq!(coarse.heights(), [5.0, 9.0]);
+    }
+
+    #[test]
+    fn slope_map_reads_45_degree_ramp_as_mid_gray() {
+        let mut heightmap = flat_heightmap();
+        for (index, pixel) in heightmap.data.chunks_exact_mut(4).enumerate() {
+            let value = (index % 4) as u8 * 10;
+            pixel.copy_from_slice(&[value, value, value, 255]);
+        }
+        // 10 / 255 of bump scale 25.5 rises one world unit per texel.
+        let slope_map = build_slope_map(&heightmap, 25.5, 1.0, HeightChannel::Luma);
+        assert_eq!(slope_map.texture_descriptor.format, TextureFormat::R8Unorm);
+        for &value in &slope_map.data {
+            assert_eq!(value, 128);
+        }
+    }
+
+    fn two_pixel_image(format: TextureFormat, data: Vec<u8>) -> Image {
+        Image::new(
+            Extent3d {
+                width: 2,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            format,
+        )
+    }
+
+    #[test]
+    fn heights_read_from_each_supported_format() {
+        let cases = [
+            (TextureFormat::R8Unorm, vec![0, 255]),
+            (
+                TextureFormat::Rgba8Unorm,
+                vec![0, 0, 0, 255, 255, 255, 255, 0],
+            ),
+            (
+                TextureFormat::Rgba8UnormSrgb,
+                vec![0, 0, 0, 255, 255, 255, 255, 0],
+            ),
+            (TextureFormat::R16Unorm, vec![0, 0, 255, 255]),
+            (TextureFormat::Rgba16Unorm, [[0u8; 8], [255; 8]].concat()),
+            // What 16-bit grayscale, gray-alpha and RGB(A) PNGs load as.
+            (TextureFormat::R16Uint, vec![0, 0, 255, 255]),
+            (
+                TextureFormat::Rg16Uint,
+                vec![0, 0, 255, 255, 255, 255, 0, 0],
+            ),
+            (TextureFormat::Rgba16Uint, [[0u8; 8], [255; 8]].concat()),
+        ];
+        for (format, data) in cases {
+            let image = two_pixel_image(format, data);
+            let field = Heightfield::from_image(&image, 2.0, HeightChannel::Luma);
+            assert_eq!(field.size(), (2, 1));
+            assert_eq!(field.heights(), [0.0, 2.0], "{format:?}");
+        }
+
+        // Luma averages red, green and blue but never alpha.
+        let rgba16 = [0u16, 65535, 0, 65535, 65535, 65535, 65535, 0]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let image = two_pixel_image(TextureFormat::Rgba16Unorm, rgba16);
+        let heights = |channel| {
+            Heightfield::from_image(&image, 3.0, channel)
+                .heights()
+                .to_vec()
+        };
+        assert_eq!(heights(HeightChannel::Luma), [1.0, 3.0]);
+        assert_eq!(heights(HeightChannel::Green), [3.0, 3.0]);
+        assert_eq!(heights(HeightChannel::Red), [0.0, 3.0]);
+        assert_eq!(heights(HeightChannel::Blue), [0.0, 3.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "unsupported heightmap format Rg8Unorm")]
+    fn unsupported_heightmap_format_is_rejected() {
+        let image = two_pixel_image(TextureFormat::Rg8Unorm, vec![0; 4]);
+        Heightfield::from_image(&image, 1.0, HeightChannel::Luma);
+    }
+}
@@ -1,26 +1,169 @@
 use bevy::core_pipeline::tonemapping::Tonemapping;
 use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::input::touch::Touches;
 use bevy::input::touchpad::TouchpadMagnify;
 use bevy::prelude::*;
 use bevy::render::camera::{OrthographicProjection, Projection, ScalingMode};
 use bevy::window::PrimaryWindow;
 
-// Camera pitch tuned so projected tiles appear with a classic 2:1 isometric ratio.
-const CAMERA_EYE_OFFSET: (f32, f32, f32) = (-1.0, 0.816_496_6, 1.0);
+// Elevation of the camera above the ground plane, in radians. 30 degrees projects tiles with a
+// classic 2:1 isometric ratio; lower pitches give a flatter tactical view.
+pub const DEFAULT_PITCH: f32 = std::f32::consts::FRAC_PI_6;
+const MIN_PITCH: f32 = 10.0 * std::f32::consts::PI / 180.0;
+// Just short of straight down, where `look_at` with a Y-up vector degenerates.
+const MAX_PITCH: f32 = 89.0 * std::f32::consts::PI / 180.0;
+// Radians per second while a pitch key is held.
+const PITCH_RATE: f32 = 0.8;
+const PITCH_UP_KEY: KeyCode = KeyCode::PageUp;
+const PITCH_DOWN_KEY: KeyCode = KeyCode::PageDown;
+const SCROLL_MODE_KEY: KeyCode = KeyCode::M;
 const CAMERA_DISTANCE_SCALE: f32 = 2.2;
 
-const TRACKPAD_PAN_SCALE: f32 = 0.1;
 const SCROLL_ZOOM_RATE: f32 = 0.02;
 const MAGNIFY_ZOOM_RATE: f32 = 1.0;
 pub const INITIAL_ZOOM: f32 = 10.0;
 const MIN_ZOOM: f32 = 4.0;
 const MAX_ZOOM: f32 = 32.0;
+// Released drags keep gliding, losing this fraction of their speed per second (as an
+// exponential rate), until they drop below the minimum speed in world units per second.
+const GLIDE_DECAY_RATE: f32 = 6.0;
+const MIN_GLIDE_SPEED: f32 = 0.05;
+// How much of each frame's drag speed feeds into the release velocity, to smooth jitter.
+const DRAG_VELOCITY_SMOOTHING: f32 = 0.5;
+// Stick deflections inside this radius are ignored; the rest is rescaled to start from zero.
+const GAMEPAD_DEAD_ZONE: f32 = 0.15;
+// Full left-stick deflection pans this many visible screen heights per second.
+const GAMEPAD_PAN_SPEED: f32 = 0.75;
+// Full right-stick deflection zooms by this exponential rate per second.
+const GAMEPAD_ZOOM_RATE: f32 = 1.5;
+
+// What scrolling does. Line-based events come from notched mouse wheels and pixel-based ones
+// from trackpads, so the wheel can zoom while two-finger scrolling still pans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScrollMode {
+    // Every scroll pans; holding Alt or Ctrl zooms instead.
+    #[default]
+    Auto,
+    // Mouse wheels zoom without a modifier; trackpad scrolling behaves as in `Auto`.
+    ZoomOnWheel,
+    // Every scroll pans, even with a modifier held; zoom with pinch, magnify or the gamepad.
+    PanOnWheel,
+}
+
+impl ScrollMode {
+    fn next(self) -> Self {
+        match self {
+            ScrollMode::Auto => ScrollMode::ZoomOnWheel,
+            ScrollMode::ZoomOnWheel => ScrollMode::PanOnWheel,
+            ScrollMode::PanOnWheel => ScrollMode::Auto,
+        }
+    }
+}
+
+// Tunable input feel; defaults match the original hard-coded constants.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct InputSettings {
+    // Screen pixels the cursor-anchored view moves per pixel of trackpad scroll.
+    pub scroll_pan_speed: f32,
+    // Multiplier on mouse and touch drags; 1.0 keeps the ground under the cursor.
+    pub drag_pan_scale: f32,
+    pub scroll_mode: ScrollMode,
+}
+
+impl Default for InputSettings {
+    fn default() -> Self {
+        Self {
+            scroll_pan_speed: 0.1,
+            drag_pan_scale: 1.0,
+            scroll_mode: ScrollMode::default(),
+        }
+    }
+}
 
 #[derive(Resource)]
 pub struct IsoCamera {
     target: Vec2,
     zoom: f32,
     last_cursor_pos: Option<Vec2>,
+    // Pan velocity in world units per second, tracked while dragging and decayed after.
+    velocity: Vec2,
+    touches: TouchPair,
+    pitch: f32,
+}
+
+// The first two fingers on the screen, by touch id, and where they were last frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct TouchPair {
+    ids: [Option<u64>; 2],
+    positions: [Vec2; 2],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TouchGesture {
+    None,
+    // One finger moved from `from` to `to` in screen space.
+    Pan {
+        from: Vec2,
+        to: Vec2,
+    },
+    // Two fingers' midpoint moved and their distance changed; `zoom_scale` is the old
+    // distance over the new one, so spreading the fingers zooms in.
+    Pinch {
+        from: Vec2,
+        to: Vec2,
+        zoom_scale: f32,
+    },
+}
+
+impl TouchPair {
+    // Only fingers held since last frame contribute, so fingers landing or lifting never make
+    // the view jump: after a pinch, the remaining finger pans from its own last position.
+    fn update(&mut self, pressed: &[(u64, Vec2)]) -> TouchGesture {
+        let previous = *self;
+        for slot in &mut self.ids {
+            if slot.is_some_and(|id| pressed.iter().all(|&(pressed_id, _)| pressed_id != id)) {
+                *slot = None;
+            }
+        }
+        for &(id, _) in pressed {
+            if !self.ids.contains(&Some(id)) {
+                if let Some(slot) = self.ids.iter_mut().find(|slot| slot.is_none()) {
+                    *slot = Some(id);
+                }
+            }
+        }
+        for (slot, position) in self.ids.iter().zip(&mut self.positions) {
+            if let Some(&(_, current)) = pressed.iter().find(|&&(id, _)| Some(id) == *slot) {
+                *position = current;
+            }
+        }
+
+        let held =
+            |index: usize| self.ids[index].is_some() && self.ids[index] == previous.ids[index];
+        match (held(0), held(1), self.ids) {
+            (true, true, _) => {
+                let old_distance = previous.positions[0].distance(previous.positions[1]);
+                let new_distance = self.positions[0].distance(self.positions[1]);
+                if new_distance <= f32::EPSILON {
+                    return TouchGesture::None;
+                }
+                TouchGesture::Pinch {
+                    from: (previous.positions[0] + previous.positions[1]) * 0.5,
+                    to: (self.positions[0] + self.positions[1]) * 0.5,
+                    zoom_scale: old_distance / new_distance,
+                }
+            }
+            (true, false, [_, None]) => TouchGesture::Pan {
+                from: previous.positions[0],
+                to: self.positions[0],
+            },
+            (false, true, [None, _]) => TouchGesture::Pan {
+                from: previous.positions[1],
+                to: self.positions[1],
+            },
+            _ => TouchGesture::None,
+        }
+    }
 }
 
 impl IsoCamera {
@@ -29,8 +172,26 @@ impl IsoCamera {
             target,
             zoom,
             last_cursor_pos: None,
+            velocity: Vec2::ZERO,
+            touches: TouchPair::default(),
+            pitch: DEFAULT_PITCH,
         }
     }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    // Clamped to stay between a near-horizontal view and (almost) straight down.
+    pub fn set_pitch(&mut self, pitch: f32) {
+        self.pitch = pitch.clamp(MIN_PITCH, MAX_PITCH);
+    }
+
+    // Jumps the view to `target`, cancelling any glide.
+    pub fn set_target(&mut self, target: Vec2) {
+        self.target = target;
+        self.velocity = Vec2::ZERO;
+    }
 }
 
 #[derive(Component)]
@@ -39,7 +200,7 @@ pub struct IsoCameraTag;
 pub fn spawn_iso_camera(mut commands: Commands) {
     let camera = IsoCamera::new(Vec2::ZERO, INITIAL_ZOOM);
     let target = Vec3::new(camera.target.x, 0.0, camera.target.y);
-    let position = target + iso_eye_direction() * (camera.zoom * CAMERA_DISTANCE_SCALE);
+    let position = target + iso_eye_direction(camera.pitch) * (camera.zoom * CAMERA_DISTANCE_SCALE);
 
     commands.insert_resource(camera);
     commands.spawn((
@@ -60,23 +221,42 @@ pub fn spawn_iso_camera(mut commands: Commands) {
     ));
 }
 
+#[allow(clippy::too_many_arguments)] // Bevy system; each argument is a system param.
 pub fn update_iso_camera(
     mut camera: ResMut<IsoCamera>,
+    time: Res<Time>,
+    mut settings: ResMut<InputSettings>,
     mut scroll_events: EventReader<MouseWheel>,
     mut magnify_events: EventReader<TouchpadMagnify>,
     mouse_buttons: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
     keys: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
     windows: Query<&Window, With<PrimaryWindow>>,
-    mut query: Query<(&Camera, &GlobalTransform, &mut Transform, &mut Projection), With<IsoCameraTag>>,
+    mut query: Query<
+        (&Camera, &GlobalTransform, &mut Transform, &mut Projection),
+        With<IsoCameraTag>,
+    >,
 ) {
-    let mut scroll_delta = Vec2::ZERO;
+    if keys.just_pressed(SCROLL_MODE_KEY) {
+        settings.scroll_mode = settings.scroll_mode.next();
+        info!("scroll mode: {:?}", settings.scroll_mode);
+    }
+    let (mut wheel_delta, mut pixel_delta) = (Vec2::ZERO, Vec2::ZERO);
     for event in scroll_events.iter() {
-        let mut delta = Vec2::new(event.x, event.y);
-        if matches!(event.unit, MouseScrollUnit::Line) {
-            delta *= 16.0;
+        let delta = Vec2::new(event.x, event.y);
+        match event.unit {
+            MouseScrollUnit::Line => wheel_delta += delta * 16.0,
+            MouseScrollUnit::Pixel => pixel_delta += delta,
         }
-        scroll_delta += delta;
     }
+    let (scroll_zoom, scroll_pan) = split_scroll(
+        settings.scroll_mode,
+        zoom_modifier_active(&keys),
+        wheel_delta,
+        pixel_delta,
+    );
     let mut magnify_delta = 0.0;
     for event in magnify_events.iter() {
         magnify_delta += event.0;
@@ -84,48 +264,107 @@ pub fn update_iso_camera(
 
     let window = windows.get_single().ok();
     let cursor_pos = window.and_then(|window| window.cursor_position());
+    // Drags pan from an explicit anchor, `last_cursor_pos`, rather than per-frame mouse motion,
+    // so pressing or releasing other buttons mid-drag can't make the view jump. Only the right
+    // button pans; left clicks belong to walking and painting.
     let dragging = mouse_buttons.pressed(MouseButton::Right);
     if !dragging {
         camera.last_cursor_pos = None;
     }
+    let dt = time.delta_seconds();
+    let pressed: Vec<(u64, Vec2)> = touches
+        .iter()
+        .map(|touch| (touch.id(), touch.position()))
+        .collect();
+    let gesture = camera.touches.update(&pressed);
+    let pitch_input = keys.pressed(PITCH_UP_KEY) as i8 - keys.pressed(PITCH_DOWN_KEY) as i8;
+    if pitch_input != 0 {
+        let pitch = camera.pitch + f32::from(pitch_input) * PITCH_RATE * dt;
+        camera.set_pitch(pitch);
+    }
+    let (pan_stick, zoom_stick) = gamepad_sticks(&gamepads, &gamepad_axes);
 
     for (camera_component, camera_transform, mut transform, mut projection) in &mut query {
         if dragging {
+            let mut drag_delta = Vec2::ZERO;
             if let (Some(current_pos), Some(last_pos)) = (cursor_pos, camera.last_cursor_pos) {
                 if let Some(world_delta) =
                     cursor_pan_delta(camera_component, camera_transform, last_pos, current_pos)
                 {
-                    camera.target += world_delta;
+                    drag_delta = world_delta * settings.drag_pan_scale;
+                    camera.target += drag_delta;
                 }
             }
+            if dt > 0.0 {
+                camera.velocity = camera
+                    .velocity
+                    .lerp(drag_delta / dt, DRAG_VELOCITY_SMOOTHING);
+            }
             camera.last_cursor_pos = cursor_pos;
+        } else {
+            let (offset, velocity) = glide(camera.velocity, dt);
+            camera.target += offset;
+            camera.velocity = velocity;
         }
 
-        if magnify_delta.abs() > 0.0 {
-            camera.zoom = (camera.zoom * (1.0 - magnify_delta * MAGNIFY_ZOOM_RATE))
+        if pan_stick != Vec2::ZERO {
+            // Stick up pans toward the top of the screen, at a speed relative to the view size.
+            let right = camera_transform.right();
+            let forward = camera_transform.forward();
+            let right = Vec2::new(right.x, right.z).normalize_or_zero();
+            let up = Vec2::new(forward.x, forward.z).normalize_or_zero();
+            let speed = GAMEPAD_PAN_SPEED * camera.zoom;
+            camera.target += (right * pan_stick.x + up * pan_stick.y) * speed * dt;
+        }
+        if zoom_stick.y != 0.0 {
+            camera.zoom = (camera.zoom * (-zoom_stick.y * GAMEPAD_ZOOM_RATE * dt).exp())
                 .clamp(MIN_ZOOM, MAX_ZOOM);
-        } else if scroll_delta.length_squared() > 0.0 {
-            if zoom_modifier_active(&keys) {
-                camera.zoom = (camera.zoom * (1.0 + scroll_delta.y * SCROLL_ZOOM_RATE))
+        }
+
+        match gesture {
+            TouchGesture::None => {}
+            TouchGesture::Pan { from, to } => {
+                if let Some(world_delta) =
+                    cursor_pan_delta(camera_component, camera_transform, from, to)
+                {
+                    camera.target += world_delta * settings.drag_pan_scale;
+                }
+            }
+            TouchGesture::Pinch {
+                from,
+                to,
+                zoom_scale,
+            } => {
+                if let Some(world_delta) =
+                    cursor_pan_delta(camera_component, camera_transform, from, to)
+                {
+                    camera.target += world_delta;
+                }
+                camera.zoom = (camera.zoom * zoom_scale).clamp(MIN_ZOOM, MAX_ZOOM);
+            }
+        }
+
+        if magnify_delta.abs() > 0.0 {
+            camera.zoom =
+                (camera.zoom * (1.0 - magnify_delta * MAGNIFY_ZOOM_RATE)).clamp(MIN_ZOOM, MAX_ZOOM);
+        } else {
+            if scroll_zoom != 0.0 {
+                camera.zoom = (camera.zoom * (1.0 + scroll_zoom * SCROLL_ZOOM_RATE))
                     .clamp(MIN_ZOOM, MAX_ZOOM);
-            } else {
-                if let Some(current_pos) = cursor_pos {
-                    let scroll_pan = scroll_delta * TRACKPAD_PAN_SCALE;
-                    let scaled_pos = current_pos + scroll_pan;
-                    if let Some(world_delta) = cursor_pan_delta(
-                        camera_component,
-                        camera_transform,
-                        current_pos,
-                        scaled_pos,
-                    ) {
-                        camera.target += world_delta;
-                    }
+            }
+            if let Some(current_pos) = cursor_pos.filter(|_| scroll_pan != Vec2::ZERO) {
+                let scaled_pos = current_pos + scroll_pan * settings.scroll_pan_speed;
+                if let Some(world_delta) =
+                    cursor_pan_delta(camera_component, camera_transform, current_pos, scaled_pos)
+                {
+                    camera.target += world_delta;
                 }
             }
         }
 
         let target = Vec3::new(camera.target.x, 0.0, camera.target.y);
-        let position = target + iso_eye_direction() * (camera.zoom * CAMERA_DISTANCE_SCALE);
+        let distance = camera.zoom * CAMERA_DISTANCE_SCALE;
+        let position = target + iso_eye_direction(camera.pitch) * distance;
         transform.translation = position;
         transform.look_at(target, Vec3::Y);
         if let Projection::Orthographic(ref mut ortho) = *projection {
@@ -135,13 +374,78 @@ pub fn update_iso_camera(
     }
 }
 
-fn iso_eye_direction() -> Vec3 {
-    Vec3::new(
-        CAMERA_EYE_OFFSET.0,
-        CAMERA_EYE_OFFSET.1,
-        CAMERA_EYE_OFFSET.2,
+// Advances a released pan by `dt` seconds, returning the offset and the decayed velocity.
+// Integrating the exponential decay exactly keeps the glide distance frame-rate independent.
+fn glide(velocity: Vec2, dt: f32) -> (Vec2, Vec2) {
+    if velocity.length() < MIN_GLIDE_SPEED {
+        return (Vec2::ZERO, Vec2::ZERO);
+    }
+    let decay = (-GLIDE_DECAY_RATE * dt).exp();
+    let offset = velocity * (1.0 - decay) / GLIDE_DECAY_RATE;
+    (offset, velocity * decay)
+}
+
+// Radial dead zone: small deflections read as zero, and the remaining range is stretched back
+// to 0..=1 so motion starts smoothly at the dead-zone edge.
+fn apply_dead_zone(stick: Vec2) -> Vec2 {
+    let length = stick.length();
+    if length <= GAMEPAD_DEAD_ZONE {
+        return Vec2::ZERO;
+    }
+    let scaled = ((length - GAMEPAD_DEAD_ZONE) / (1.0 - GAMEPAD_DEAD_ZONE)).min(1.0);
+    stick * (scaled / length)
+}
+
+// The left (pan) and right (zoom) sticks after the dead zone. With several controllers
+// connected, the first one drives the camera.
+fn gamepad_sticks(gamepads: &Gamepads, axes: &Axis<GamepadAxis>) -> (Vec2, Vec2) {
+    let Some(gamepad) = gamepads.iter().next() else {
+        return (Vec2::ZERO, Vec2::ZERO);
+    };
+    let stick = |x, y| {
+        let axis = |axis_type| {
+            axes.get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or(0.0)
+        };
+        apply_dead_zone(Vec2::new(axis(x), axis(y)))
+    };
+    (
+        stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+        stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
     )
-    .normalize()
+}
+
+// Unit vector from the target toward the eye, looking along the map's diagonal from `pitch`
+// radians above the ground. Panning and cursor picking cast rays through the camera transform,
+// so they follow whatever pitch this produces.
+fn iso_eye_direction(pitch: f32) -> Vec3 {
+    let horizontal = Vec3::new(-1.0, 0.0, 1.0).normalize() * pitch.cos();
+    horizontal + Vec3::Y * pitch.sin()
+}
+
+// Where the shadow of a point `height` above the ground falls, relative to the point's foot, in
+// world units along the screen's right and up axes for a camera at `pitch`. `sun_dir` is
+// (east, up, north) like `solar_direction`; there is no shadow once the sun is at or below the
+// horizon.
+pub fn shadow_offset(sun_dir: Vec3, height: f32, pitch: f32) -> Option<Vec2> {
+    if sun_dir.y <= 0.0 {
+        return None;
+    }
+    let ground = Vec3::new(-sun_dir.x, 0.0, -sun_dir.z) * (height / sun_dir.y);
+    let forward = -iso_eye_direction(pitch);
+    let right = forward.cross(Vec3::Y).normalize();
+    let up = right.cross(forward);
+    Some(Vec2::new(ground.dot(right), ground.dot(up)))
+}
+
+// Splits this frame's wheel and trackpad scroll, both in pixels, into a zoom amount (vertical
+// scroll only) and a pan offset according to `mode`.
+fn split_scroll(mode: ScrollMode, modifier: bool, wheel: Vec2, pixel: Vec2) -> (f32, Vec2) {
+    match mode {
+        ScrollMode::Auto | ScrollMode::ZoomOnWheel if modifier => ((wheel + pixel).y, Vec2::ZERO),
+        ScrollMode::ZoomOnWheel => (wheel.y, pixel),
+        ScrollMode::Auto | ScrollMode::PanOnWheel => (0.0, wheel + pixel),
+    }
 }
 
 fn zoom_modifier_active(keys: &Input<KeyCode>) -> bool {
@@ -159,7 +463,10 @@ fn cursor_pan_delta(
 ) -> Option<Vec2> {
     let world_from = cursor_world_on_plane(camera, camera_transform, from)?;
     let world_to = cursor_world_on_plane(camera, camera_transform, to)?;
-    Some(Vec2::new(world_from.x - world_to.x, world_from.z - world_to.z))
+    Some(Vec2::new(
+        world_from.x - world_to.x,
+        world_from.z - world_to.z,
+    ))
 }
 
 pub fn cursor_world_on_plane(
@@ -177,3 +484,155 @@ pub fn cursor_world_on_plane(
     }
     Some(ray.origin + ray.direction * t)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glide_distance_is_frame_rate_independent() {
+        // Half a second of gliding, rendered at different frame rates.
+        let total = |steps: usize| {
+            let dt = 0.5 / steps as f32;
+            let mut velocity = Vec2::new(3.0, -1.0);
+            let mut distance = Vec2::ZERO;
+            for _ in 0..steps {
+                let (offset, decayed) = glide(velocity, dt);
+                distance += offset;
+                velocity = decayed;
+            }
+            (distance, velocity)
+        };
+        let (slow, slow_velocity) = total(15);
+        let (fast, fast_velocity) = total(72);
+        assert!((slow - fast).length() < 1e-4, "slow={slow} fast={fast}");
+        assert!((slow_velocity - fast_velocity).length() < 1e-4);
+        let expected_velocity = Vec2::new(3.0, -1.0) * (-GLIDE_DECAY_RATE * 0.5).exp();
+        assert!((slow_velocity - expected_velocity).length() < 1e-4);
+    }
+
+    #[test]
+    fn slow_glide_stops() {
+        assert_eq!(glide(Vec2::new(0.01, 0.0), 0.016), (Vec2::ZERO, Vec2::ZERO));
+    }
+
+    #[test]
+    fn default_pitch_matches_the_classic_isometric_eye_offset() {
+        let classic = Vec3::new(-1.0, 0.816_496_6, 1.0).normalize();
+        assert!((iso_eye_direction(DEFAULT_PITCH) - classic).length() < 1e-6);
+
+        let mut camera = IsoCamera::new(Vec2::ZERO, INITIAL_ZOOM);
+        camera.set_pitch(std::f32::consts::FRAC_PI_2);
+        assert_eq!(camera.pitch(), MAX_PITCH);
+        camera.set_pitch(0.0);
+        assert_eq!(camera.pitch(), MIN_PITCH);
+        assert!((iso_eye_direction(camera.pitch()).length() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn shadows_fall_away_from_the_sun_and_vanish_at_night() {
+        // At 45 degrees due east a unit-high point casts a unit-long shadow due west, which the
+        // camera sees down and to the left.
+        let east = Vec3::new(1.0, 1.0, 0.0).normalize();
+        let offset = shadow_offset(east, 1.0, DEFAULT_PITCH).unwrap();
+        let expected = Vec2::new(-1.0, -DEFAULT_PITCH.sin()) * std::f32::consts::FRAC_1_SQRT_2;
+        assert!((offset - expected).length() < 1e-6, "{offset}");
+
+        assert!(shadow_offset(Vec3::Y, 2.0, DEFAULT_PITCH).unwrap().length() < 1e-6);
+        assert_eq!(
+            shadow_offset(Vec3::new(1.0, -0.1, 0.0), 1.0, DEFAULT_PITCH),
+            None
+        );
+    }
+
+    #[test]
+    fn scroll_mode_decides_whether_wheels_zoom_or_pan() {
+        let (wheel, pixel) = (Vec2::new(0.0, 16.0), Vec2::new(3.0, -2.0));
+        let split = |mode, modifier| split_scroll(mode, modifier, wheel, pixel);
+        assert_eq!(split(ScrollMode::Auto, false), (0.0, wheel + pixel));
+        assert_eq!(split(ScrollMode::Auto, true), (14.0, Vec2::ZERO));
+        assert_eq!(split(ScrollMode::ZoomOnWheel, false), (16.0, pixel));
+        assert_eq!(split(ScrollMode::ZoomOnWheel, true), (14.0, Vec2::ZERO));
+        assert_eq!(split(ScrollMode::PanOnWheel, true), (0.0, wheel + pixel));
+        assert_eq!(ScrollMode::Auto.next().next().next(), ScrollMode::Auto);
+    }
+
+    #[test]
+    fn dead_zone_drops_small_deflections_and_rescales_the_rest() {
+        assert_eq!(apply_dead_zone(Vec2::new(0.1, -0.05)), Vec2::ZERO);
+        assert!((apply_dead_zone(Vec2::new(0.0, 1.0)) - Vec2::Y).length() < 1e-6);
+        let halfway = apply_dead_zone(Vec2::new(-(1.0 + GAMEPAD_DEAD_ZONE) * 0.5, 0.0));
+        assert!(
+            (halfway - Vec2::new(-0.5, 0.0)).length() < 1e-6,
+            "{halfway}"
+        );
+        // Diagonals past the unit circle are capped at full speed.
+        assert!((apply_dead_zone(Vec2::ONE).length() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn connected_gamepad_sticks_reach_the_camera() {
+        use bevy::input::gamepad::{GamepadAxisChangedEvent, GamepadInfo};
+        use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent, GamepadEvent};
+        use bevy::input::InputPlugin;
+
+        // The same events gilrs sends when a pad is plugged in and its left stick pushed.
+        let mut app = App::new();
+        app.add_plugins(InputPlugin);
+        let pad = Gamepad::new(0);
+        let info = GamepadInfo {
+            name: "pad".to_owned(),
+        };
+        let connected = GamepadConnectionEvent::new(pad, GamepadConnection::Connected(info));
+        app.world.send_event(GamepadEvent::Connection(connected));
+        let pushed = GamepadAxisChangedEvent::new(pad, GamepadAxisType::LeftStickX, 1.0);
+        app.world.send_event(GamepadEvent::Axis(pushed));
+        app.update();
+
+        let gamepads = app.world.resource::<Gamepads>();
+        let axes = app.world.resource::<Axis<GamepadAxis>>();
+        assert_eq!(gamepad_sticks(gamepads, axes), (Vec2::X, Vec2::ZERO));
+        assert_eq!(
+            gamepad_sticks(&Gamepads::default(), axes),
+            (Vec2::ZERO, Vec2::ZERO)
+        );
+    }
+
+    #[test]
+    fn touches_pan_with_one_finger_and_pinch_with_two() {
+        let mut pair = TouchPair::default();
+        assert_eq!(
+            pair.update(&[(7, Vec2::new(10.0, 10.0))]),
+            TouchGesture::None
+        );
+        assert_eq!(
+            pair.update(&[(7, Vec2::new(14.0, 10.0))]),
+            TouchGesture::Pan {
+                from: Vec2::new(10.0, 10.0),
+                to: Vec2::new(14.0, 10.0)
+            }
+        );
+
+        // A second finger landing starts a pinch on the following frame.
+        let landing = [(7, Vec2::new(14.0, 10.0)), (9, Vec2::new(34.0, 10.0))];
+        assert_eq!(pair.update(&landing), TouchGesture::None);
+        let spread = [(7, Vec2::new(4.0, 10.0)), (9, Vec2::new(44.0, 10.0))];
+        assert_eq!(
+            pair.update(&spread),
+            TouchGesture::Pinch {
+                from: Vec2::new(24.0, 10.0),
+                to: Vec2::new(24.0, 10.0),
+                zoom_scale: 0.5
+            }
+        );
+
+        // Lifting the first finger pans from the second finger's own last position.
+        assert_eq!(
+            pair.update(&[(9, Vec2::new(45.0, 10.0))]),
+            TouchGesture::Pan {
+                from: Vec2::new(44.0, 10.0),
+                to: Vec2::new(45.0, 10.0)
+            }
+        );
+    }
+}
@@ -1,3 +1,5 @@
+pub mod builder;
 pub mod engine;
+pub mod scene;
 pub mod solar;
 pub mod units;
@@ -1,23 +1,30 @@
-use bevy::prelude::*;
+use bevy::animation::AnimationPlayer;
+use bevy::app::PostUpdate;
 use bevy::asset::LoadState;
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
 use bevy::log::{Level, LogPlugin};
-use bevy::pbr::DirectionalLightShadowMap;
 use bevy::math::Ray;
+use bevy::pbr::DirectionalLightShadowMap;
+use bevy::prelude::*;
 use bevy::render::mesh::{Indices, Mesh};
 use bevy::render::render_resource::PrimitiveTopology;
 use bevy::render::texture::ImagePlugin;
-use bevy::animation::AnimationPlayer;
-use bevy::app::PostUpdate;
 use bevy::window::PrimaryWindow;
-use rand::Rng;
+use dustfall::solar::{self, Location};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::collections::HashSet;
 use std::f32::consts::TAU;
-use dustfall::solar::{self, Location};
 
+mod grid_overlay;
 mod heightmap_normal;
 mod isometric;
+mod minimap;
+mod terrain_shadow;
 mod texture_atlas;
+mod tile_editor;
 
+const WINDOW_TITLE: &str = "Dustfall";
 const GRID_WIDTH: usize = 256;
 const GRID_HEIGHT: usize = 256;
 // World units are in _meters_
@@ -31,7 +38,11 @@ const ASTRONAUT_WALK_ANIM: &str = "models/astronaut/astronaut-textured.glb#Anima
 const HEIGHTMAP_BUMP_SLOPE: f32 = 16.0;
 const HEIGHTMAP_BUMP_SCALE: f32 = HEIGHTMAP_BUMP_SLOPE * TILE_SIZE;
 const HEIGHTMAP_PATCH_SIZE: usize = 128;
-const ASTRONAUT_SCALE: f32 = 0.42;  // Scales to ~1.7m
+// The 1024x1024 heightmap splits into 8x8 atlas patches.
+const TILE_VARIANTS: u32 = 64;
+const TILE_FLIP_SEED: u64 = 0x5EED_F11B;
+const ASTRONAUT_SCALE: f32 = 0.42; // Scales to ~1.7m
+const ASTRONAUT_HEIGHT: f32 = 1.7;
 const ASTRONAUT_WALK_SPEED: f32 = 1.2;
 const ASTRONAUT_TURN_SPEED: f32 = 4.0;
 const ASTRONAUT_STOP_DISTANCE: f32 = 0.05;
@@ -41,6 +52,10 @@ const DEFAULT_LOCATION: Location = Location {
     latitude: 22.5 * (TAU / 360.0),
     longitude: 137.4 * (TAU / 360.0),
 };
+// Directional light strength with the sun straight overhead.
+const SUN_ILLUMINANCE: f32 = 18000.0;
+// The sun moves about a quarter of a degree per minute, so it is resampled once a minute.
+const SUN_CACHE_SECONDS: f32 = 60.0;
 
 #[derive(States, Debug, Clone, Eq, PartialEq, Hash, Default)]
 enum AppState {
@@ -56,6 +71,25 @@ struct TileMap {
     tiles: Vec<u32>,
 }
 
+#[derive(Resource, Default)]
+struct DebugOverlay {
+    enabled: bool,
+}
+
+// Where on Mars the sun is computed for.
+#[derive(Resource)]
+struct SunSite {
+    location: Location,
+}
+
+impl Default for SunSite {
+    fn default() -> Self {
+        Self {
+            location: DEFAULT_LOCATION,
+        }
+    }
+}
+
 #[derive(Resource)]
 struct GameAssets {
     heightmap: Handle<Image>,
@@ -64,10 +98,9 @@ struct GameAssets {
 }
 
 #[derive(Resource)]
-#[allow(dead_code)]
 struct TerrainAssets {
     atlas: texture_atlas::TextureAtlas,
-    material: Handle<StandardMaterial>,
+    material: Handle<terrain_shadow::TerrainMaterial>,
 }
 
 #[derive(Component)]
@@ -97,13 +130,65 @@ struct AstronautAnimations {
     walk: Handle<AnimationClip>,
 }
 
+struct MapArgs {
+    width: usize,
+    height: usize,
+    seed: Option<u64>,
+}
+
+const USAGE: &str = "usage: dustfall [width height [seed]] (sizes must be multiples of 16)";
+
+fn parse_map_args(args: &[String]) -> Option<MapArgs> {
+    let parse_side = |value: &String| {
+        value
+            .parse::<usize>()
+            .ok()
+            .filter(|&side| side > 0 && side.is_multiple_of(CHUNK_SIZE))
+    };
+    match args {
+        [] => Some(MapArgs {
+            width: GRID_WIDTH,
+            height: GRID_HEIGHT,
+            seed: None,
+        }),
+        [width, height] => Some(MapArgs {
+            width: parse_side(width)?,
+            height: parse_side(height)?,
+            seed: None,
+        }),
+        [width, height, seed] => Some(MapArgs {
+            width: parse_side(width)?,
+            height: parse_side(height)?,
+            seed: Some(seed.parse().ok()?),
+        }),
+        _ => None,
+    }
+}
+
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(map_args) = parse_map_args(&args) else {
+        eprintln!("{USAGE}");
+        std::process::exit(2);
+    };
+    let mut rng = match map_args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
     App::new()
         .insert_resource(ClearColor(Color::rgb(0.05, 0.05, 0.08)))
         .add_state::<AppState>()
         .add_plugins(
             DefaultPlugins
                 .set(ImagePlugin::default_nearest())
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: WINDOW_TITLE.to_string(),
+                        ..default()
+                    }),
+                    ..default()
+                })
                 // Bevy doesn't support Step interpolation for glTF animations.
                 // Re-export the GLB with Linear interpolation to remove this filter.
                 .set(LogPlugin {
@@ -111,24 +196,30 @@ fn main() {
                     filter: "wgpu=error,naga=warn,bevy_gltf::loader=error".to_string(),
                 }),
         )
-        .insert_resource(random_map(GRID_WIDTH, GRID_HEIGHT))
+        .add_plugins(FrameTimeDiagnosticsPlugin)
+        .add_plugins(MaterialPlugin::<terrain_shadow::TerrainMaterial>::default())
+        .insert_resource(TileMap::with_rng(map_args.width, map_args.height, &mut rng))
+        .init_resource::<DebugOverlay>()
+        .init_resource::<SunSite>()
+        .init_resource::<isometric::InputSettings>()
+        .init_resource::<minimap::MinimapLayout>()
+        .init_resource::<tile_editor::TileEditor>()
+        .init_resource::<grid_overlay::GridOverlay>()
         .add_systems(Startup, (isometric::spawn_iso_camera, load_assets))
-        .add_systems(
-            OnEnter(AppState::Loading),
-            spawn_loading_indicator,
-        )
+        .add_systems(OnEnter(AppState::Loading), spawn_loading_indicator)
         .add_systems(OnExit(AppState::Loading), despawn_loading_indicator)
         .add_systems(
             Update,
-            (
-                check_loading_ready,
-                animate_loading_indicator,
-            )
-                .run_if(in_state(AppState::Loading)),
+            (check_loading_ready, animate_loading_indicator).run_if(in_state(AppState::Loading)),
         )
         .add_systems(
             OnEnter(AppState::Running),
-            (setup_lighting, prepare_terrain_assets, setup_astronaut),
+            (
+                setup_lighting,
+                prepare_terrain_assets,
+                setup_astronaut,
+                minimap::spawn_minimap,
+            ),
         )
         .add_systems(
             Update,
@@ -136,7 +227,12 @@ fn main() {
                 init_scene_animations,
                 spawn_tile_meshes,
                 isometric::update_iso_camera,
+                (minimap::update_minimap, minimap::minimap_click).chain(),
+                tile_editor::edit_tiles.before(spawn_tile_meshes),
                 update_sun_light,
+                terrain_shadow::update_terrain_shadows.after(update_sun_light),
+                grid_overlay::update_grid_overlay,
+                (toggle_debug_overlay, update_debug_overlay).chain(),
                 (update_astronaut_movement, update_astronaut_animation_state).chain(),
             )
                 .run_if(in_state(AppState::Running)),
@@ -215,16 +311,12 @@ fn check_loading_ready(
     animations: Res<AstronautAnimations>,
     mut next_state: ResMut<NextState<AppState>>,
 ) {
-    let heightmap_loaded =
-        asset_server.get_load_state(&assets.heightmap) == LoadState::Loaded;
-    let albedo_loaded =
-        asset_server.get_load_state(&assets.albedo) == LoadState::Loaded;
+    let heightmap_loaded = asset_server.get_load_state(&assets.heightmap) == LoadState::Loaded;
+    let albedo_loaded = asset_server.get_load_state(&assets.albedo) == LoadState::Loaded;
     let astronaut_scene_loaded =
         asset_server.get_load_state(&assets.astronaut_scene) == LoadState::Loaded;
-    let astronaut_idle_loaded =
-        asset_server.get_load_state(&animations.idle) == LoadState::Loaded;
-    let astronaut_walk_loaded =
-        asset_server.get_load_state(&animations.walk) == LoadState::Loaded;
+    let astronaut_idle_loaded = asset_server.get_load_state(&animations.idle) == LoadState::Loaded;
+    let astronaut_walk_loaded = asset_server.get_load_state(&animations.walk) == LoadState::Loaded;
 
     if heightmap_loaded
         && albedo_loaded
@@ -244,36 +336,46 @@ fn setup_lighting(mut commands: Commands) {
     commands.insert_resource(DirectionalLightShadowMap { size: 2048 });
     commands.spawn(DirectionalLightBundle {
         directional_light: DirectionalLight {
-            illuminance: 18000.0,
+            illuminance: SUN_ILLUMINANCE,
             shadows_enabled: true,
             shadow_depth_bias: 0.02,
             shadow_normal_bias: 1.0,
             ..default()
         },
-        transform: Transform::from_rotation(Quat::from_euler(
-            EulerRot::XYZ,
-            -0.9,
-            -0.6,
-            0.0,
-        )),
+        transform: Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, -0.9, -0.6, 0.0)),
         ..default()
     });
 }
 
 fn update_sun_light(
     time: Res<Time>,
-    mut lights: Query<&mut Transform, With<DirectionalLight>>,
+    site: Res<SunSite>,
+    mut cache: Local<Option<solar::SunCache>>,
+    mut lights: Query<(&mut Transform, &mut DirectionalLight)>,
 ) {
+    if site.is_changed() {
+        *cache = None;
+    }
+    let cache = cache
+        .get_or_insert_with(|| solar::SunCache::new(solar::MARS, site.location, SUN_CACHE_SECONDS));
     let time_seconds = time.elapsed_seconds();
-    let (x, y, z) = solar::solar_direction(&solar::MARS, DEFAULT_LOCATION, time_seconds);
-    let sun_dir = Vec3::new(x, y, z);
-    let light_dir = -sun_dir.normalize_or_zero();
-    let rotation = Quat::from_rotation_arc(Vec3::NEG_Z, light_dir);
-    for mut transform in &mut lights {
+    // The solar module's (east, up, north) maps directly onto +X, +Y, +Z.
+    let (x, y, z) = cache.direction(time_seconds);
+    let (rotation, illuminance) = sun_light(Vec3::new(x, y, z));
+    for (mut transform, mut light) in &mut lights {
         transform.rotation = rotation;
+        light.illuminance = illuminance;
     }
 }
 
+// Points the light away from the sun and scales it by the sun's height, so it fades out at
+// the horizon and only the ambient light is left at night.
+fn sun_light(sun_dir: Vec3) -> (Quat, f32) {
+    let sun_dir = sun_dir.normalize_or_zero();
+    let rotation = Quat::from_rotation_arc(Vec3::NEG_Z, -sun_dir);
+    (rotation, SUN_ILLUMINANCE * sun_dir.y.max(0.0))
+}
+
 fn setup_astronaut(mut commands: Commands, assets: Res<GameAssets>) {
     let spawn_translation = Vec3::new(2.0, 0.0, 0.5);
     commands.spawn((
@@ -298,9 +400,10 @@ fn setup_astronaut(mut commands: Commands, assets: Res<GameAssets>) {
 
 fn prepare_terrain_assets(
     mut commands: Commands,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut materials: ResMut<Assets<terrain_shadow::TerrainMaterial>>,
     mut images: ResMut<Assets<Image>>,
     assets: Res<GameAssets>,
+    map: Res<TileMap>,
 ) {
     let heightmap_image = images
         .get(&assets.heightmap)
@@ -311,34 +414,59 @@ fn prepare_terrain_assets(
         .expect("albedo image not loaded")
         .clone();
     assert_eq!(
-        heightmap_image.texture_descriptor.size,
-        albedo_image.texture_descriptor.size,
+        heightmap_image.texture_descriptor.size, albedo_image.texture_descriptor.size,
         "albedo map must match heightmap dimensions"
     );
 
-    let normal_map = heightmap_normal::build_heightmap_normal_map(
+    let heightfield = heightmap_normal::Heightfield::from_image(
         &heightmap_image,
         HEIGHTMAP_BUMP_SCALE,
+        heightmap_normal::HeightChannel::Luma,
+    );
+    let normal_map = heightmap_normal::build_normal_map(
+        &heightfield,
         TILE_SIZE,
+        heightmap_normal::NormalSpace::TangentZUp,
     );
     let normal_handle = images.add(normal_map);
     let atlas = texture_atlas::TextureAtlas::from_image(
         &heightmap_image,
         HEIGHTMAP_PATCH_SIZE,
+        None,
         normal_handle,
+    )
+    .unwrap_or_else(|error| panic!("heightmap atlas: {error}"))
+    // Indices wrap at the atlas tile count, so pin it to the variants the map generators use.
+    // This panics if the heightmap has fewer patches than that.
+    .with_tile_count(TILE_VARIANTS as usize);
+    let (patch_texels, _) = atlas.tile_size_pixels();
+    let shadows = terrain_shadow::TerrainShadows::new(
+        &heightfield,
+        TILE_SIZE,
+        patch_texels,
+        &map,
+        &mut images,
     );
-    let material = materials.add(StandardMaterial {
-        base_color: Color::rgb(1.0, 1.0, 1.0),
-        base_color_texture: Some(assets.albedo.clone()),
-        normal_map_texture: Some(atlas.handle.clone()),
-        perceptual_roughness: 0.9,
-        cull_mode: None,
-        ..default()
+    let material = materials.add(terrain_shadow::TerrainMaterial {
+        map_rect: terrain_shadow::map_rect(&map),
+        albedo: assets.albedo.clone(),
+        normal_map: atlas.handle.clone(),
+        shadow_mask: shadows.mask().clone(),
     });
 
+    commands.insert_resource(shadows);
     commands.insert_resource(TerrainAssets { atlas, material });
 }
 
+// What the last chunk scan saw. While it stays the same, no chunk can have come into view or
+// gone missing, so the scan is skipped.
+#[derive(PartialEq)]
+struct ChunkScan {
+    camera_transform: GlobalTransform,
+    viewport_size: Option<Vec2>,
+    chunk_count: usize,
+}
+
 fn spawn_tile_meshes(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -346,13 +474,22 @@ fn spawn_tile_meshes(
     terrain: Res<TerrainAssets>,
     chunks: Query<&TerrainChunk>,
     camera_query: Query<(&Camera, &GlobalTransform), With<isometric::IsoCameraTag>>,
+    mut last_scan: Local<Option<ChunkScan>>,
 ) {
     let Ok((camera, camera_transform)) = camera_query.get_single() else {
         return;
     };
+    let scan = ChunkScan {
+        camera_transform: *camera_transform,
+        viewport_size: camera.logical_viewport_size(),
+        chunk_count: chunks.iter().len(),
+    };
+    if !map.is_changed() && last_scan.as_ref() == Some(&scan) {
+        return;
+    }
+    *last_scan = Some(scan);
 
-    let chunk_world_size = Vec2::splat(CHUNK_SIZE as f32 * TILE_SIZE);
-    let visible = visible_chunks(chunk_world_size, 0.0, camera, camera_transform);
+    let visible = visible_map_chunks(&map, camera, camera_transform);
     if visible.is_empty() {
         return;
     }
@@ -362,24 +499,10 @@ fn spawn_tile_meshes(
         existing.insert(chunk.coord);
     }
 
-    let chunks_x = map.width / CHUNK_SIZE;
-    let chunks_y = map.height / CHUNK_SIZE;
     let half_w = map.width as f32 * TILE_SIZE * 0.5;
     let half_h = map.height as f32 * TILE_SIZE * 0.5;
-    let offset_x = (half_w / chunk_world_size.x).round() as i32;
-    let offset_y = (half_h / chunk_world_size.y).round() as i32;
 
     for (chunk_x, chunk_y) in visible {
-        let map_chunk_x = chunk_x + offset_x;
-        let map_chunk_y = chunk_y + offset_y;
-        if map_chunk_x < 0 || map_chunk_y < 0 {
-            continue;
-        }
-        let chunk_x = map_chunk_x as usize;
-        let chunk_y = map_chunk_y as usize;
-        if chunk_x >= chunks_x || chunk_y >= chunks_y {
-            continue;
-        }
         let coord = IVec2::new(chunk_x as i32, chunk_y as i32);
         if existing.contains(&coord) {
             continue;
@@ -387,15 +510,151 @@ fn spawn_tile_meshes(
 
         let mut mesh = build_chunk_mesh(&map, &terrain.atlas, chunk_x, chunk_y, half_w, half_h);
         let _ = mesh.generate_tangents();
-        commands.spawn(PbrBundle {
-            mesh: meshes.add(mesh),
-            material: terrain.material.clone(),
-            ..default()
+        commands
+            .spawn(MaterialMeshBundle {
+                mesh: meshes.add(mesh),
+                material: terrain.material.clone(),
+                ..default()
+            })
+            .insert(TerrainChunk { coord });
+    }
+}
+
+// Visible chunks clipped to the map, as map chunk coordinates.
+fn visible_map_chunks(
+    map: &TileMap,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Vec<(usize, usize)> {
+    let Some(screen_size) = camera.logical_viewport_size() else {
+        return Vec::new();
+    };
+    map_chunks_in_view(map, screen_size, |corner| {
+        camera.viewport_to_world(camera_transform, corner)
+    })
+}
+
+// `viewport_to_world` casts the ray under a screen position, so tests can stand in for a camera.
+fn map_chunks_in_view(
+    map: &TileMap,
+    screen_size: Vec2,
+    viewport_to_world: impl Fn(Vec2) -> Option<Ray>,
+) -> Vec<(usize, usize)> {
+    let chunk_world_size = Vec2::splat(CHUNK_SIZE as f32 * TILE_SIZE);
+    let chunks_x = map.width / CHUNK_SIZE;
+    let chunks_y = map.height / CHUNK_SIZE;
+    let map_origin = Vec2::new(
+        -(map.width as f32) * TILE_SIZE * 0.5,
+        -(map.height as f32) * TILE_SIZE * 0.5,
+    );
+
+    visible_chunks(
+        map_origin,
+        chunk_world_size,
+        0.0,
+        screen_size,
+        viewport_to_world,
+    )
+    .into_iter()
+    .filter_map(|(chunk_x, chunk_y)| {
+        if chunk_x < 0 || chunk_y < 0 {
+            return None;
+        }
+        let chunk_x = chunk_x as usize;
+        let chunk_y = chunk_y as usize;
+        if chunk_x >= chunks_x || chunk_y >= chunks_y {
+            return None;
+        }
+        Some((chunk_x, chunk_y))
+    })
+    .collect()
+}
+
+fn toggle_debug_overlay(
+    keys: Res<Input<KeyCode>>,
+    mut overlay: ResMut<DebugOverlay>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !keys.just_pressed(KeyCode::F) {
+        return;
+    }
+    overlay.enabled = !overlay.enabled;
+    if !overlay.enabled {
+        for mut window in &mut windows {
+            window.title = WINDOW_TITLE.to_string();
+        }
+    }
+}
+
+// Shown in the window title, so the overlay costs no draw calls.
+fn update_debug_overlay(
+    overlay: Res<DebugOverlay>,
+    diagnostics: Res<DiagnosticsStore>,
+    map: Res<TileMap>,
+    iso_camera: Res<isometric::IsoCamera>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<isometric::IsoCameraTag>>,
+    suns: Query<&Transform, With<DirectionalLight>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !overlay.enabled {
+        return;
+    }
+    // The sun light shines along its forward axis, so the sun lies the other way.
+    let pitch = iso_camera.pitch();
+    let shadow = suns
+        .get_single()
+        .ok()
+        .and_then(|sun| isometric::shadow_offset(-sun.forward(), ASTRONAUT_HEIGHT, pitch))
+        .map_or("shadow -".to_string(), |offset| {
+            format!("shadow ({:.2}, {:.2})", offset.x, offset.y)
+        });
+    let fps = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or(0.0);
+    let camera = camera_query.get_single().ok();
+    let visible_tiles = camera
+        .map(|(camera, camera_transform)| {
+            visible_map_chunks(&map, camera, camera_transform).len() * CHUNK_SIZE * CHUNK_SIZE
         })
-        .insert(TerrainChunk { coord });
+        .unwrap_or(0);
+    for mut window in &mut windows {
+        let cursor_world = window.cursor_position().zip(camera).and_then(
+            |(cursor_pos, (camera, camera_transform))| {
+                isometric::cursor_world_on_plane(camera, camera_transform, cursor_pos)
+            },
+        );
+        let cursor = match cursor_world {
+            Some(world) => {
+                let (tile_x, tile_y) = world_to_tile(&map, Vec2::new(world.x, world.z));
+                let inside = if map.contains(tile_x, tile_y) {
+                    "inside"
+                } else {
+                    "outside"
+                };
+                format!(
+                    "cursor ({:.2}, {:.2}) tile ({tile_x}, {tile_y}) {inside}",
+                    world.x, world.z
+                )
+            }
+            None => "cursor -".to_string(),
+        };
+        window.title = format!(
+            "{WINDOW_TITLE} | {fps:.0} fps | {visible_tiles} tiles visible | {cursor} | {shadow}"
+        );
     }
 }
 
+// World XZ position to the tile containing it, which may lie outside the map.
+fn world_to_tile(map: &TileMap, world: Vec2) -> (i32, i32) {
+    let half_w = map.width as f32 * TILE_SIZE * 0.5;
+    let half_h = map.height as f32 * TILE_SIZE * 0.5;
+    (
+        ((world.x + half_w) / TILE_SIZE).floor() as i32,
+        ((world.y + half_h) / TILE_SIZE).floor() as i32,
+    )
+}
+
 fn init_scene_animations(
     astronaut_animations: Res<AstronautAnimations>,
     astronaut_roots: Query<Entity, With<Astronaut>>,
@@ -432,11 +691,7 @@ fn remove_cameras<T: Component>(
     }
 }
 
-fn is_descendant_of(
-    mut entity: Entity,
-    roots: &[Entity],
-    parents: &Query<&Parent>,
-) -> bool {
+fn is_descendant_of(mut entity: Entity, roots: &[Entity], parents: &Query<&Parent>) -> bool {
     loop {
         if roots.contains(&entity) {
             return true;
@@ -448,77 +703,60 @@ fn is_descendant_of(
     }
 }
 
-fn build_grid_meshes(map: &TileMap, atlas: &texture_atlas::TextureAtlas) -> Vec<Mesh> {
-    assert!(
-        map.width % CHUNK_SIZE == 0 && map.height % CHUNK_SIZE == 0,
-        "map dimensions must be divisible by chunk size"
-    );
-
-    let chunks_x = map.width / CHUNK_SIZE;
-    let chunks_y = map.height / CHUNK_SIZE;
-    let half_w = map.width as f32 * TILE_SIZE * 0.5;
-    let half_h = map.height as f32 * TILE_SIZE * 0.5;
-
-    let mut meshes = Vec::with_capacity(chunks_x * chunks_y);
-    for chunk_y in 0..chunks_y {
-        for chunk_x in 0..chunks_x {
-            let mesh = build_chunk_mesh(map, atlas, chunk_x, chunk_y, half_w, half_h);
-            meshes.push(mesh);
-        }
-    }
-
-    meshes
-}
-
+// Chunk coordinates are counted from `origin` on the ground plane.
 fn visible_chunks(
+    origin: Vec2,
     chunk_world_size: Vec2,
     plane_y: f32,
-    camera: &Camera,
-    camera_transform: &GlobalTransform,
+    screen_size: Vec2,
+    viewport_to_world: impl Fn(Vec2) -> Option<Ray>,
 ) -> Vec<(i32, i32)> {
-    assert!(chunk_world_size.x > 0.0, "chunk world width must be positive");
-    assert!(chunk_world_size.y > 0.0, "chunk world height must be positive");
-    let Some(viewport_size) = camera.logical_viewport_size() else {
-        return Vec::new();
-    };
+    assert!(
+        chunk_world_size.x > 0.0,
+        "chunk world width must be positive"
+    );
+    assert!(
+        chunk_world_size.y > 0.0,
+        "chunk world height must be positive"
+    );
 
     let corners = [
         Vec2::ZERO,
-        Vec2::new(viewport_size.x, 0.0),
-        Vec2::new(0.0, viewport_size.y),
-        Vec2::new(viewport_size.x, viewport_size.y),
+        Vec2::new(screen_size.x, 0.0),
+        Vec2::new(0.0, screen_size.y),
+        Vec2::new(screen_size.x, screen_size.y),
     ];
 
-    let rays: Vec<_> = corners
-        .into_iter()
-        .filter_map(|corner| camera.viewport_to_world(camera_transform, corner))
-        .collect();
+    let rays: Vec<_> = corners.into_iter().filter_map(viewport_to_world).collect();
 
     let points: Vec<_> = rays
         .iter()
         .filter_map(|ray| project_ray_onto_xz_plane(ray, plane_y))
         .collect();
 
+    let ground: Vec<Vec2> = points
+        .iter()
+        .map(|pos| Vec2::new(pos.x, pos.z) - origin)
+        .collect();
+    chunks_covering(&ground, chunk_world_size)
+}
+
+// Every chunk overlapping the bounding box of the projected viewport corners. Ground is flat,
+// so the box contains the whole visible quad and no visible chunk is culled.
+fn chunks_covering(points: &[Vec2], chunk_world_size: Vec2) -> Vec<(i32, i32)> {
     let min_x = points.iter().map(|pos| pos.x).reduce(f32::min);
     let max_x = points.iter().map(|pos| pos.x).reduce(f32::max);
-    let min_y = points.iter().map(|pos| pos.z).reduce(f32::min);
-    let max_y = points.iter().map(|pos| pos.z).reduce(f32::max);
+    let min_y = points.iter().map(|pos| pos.y).reduce(f32::min);
+    let max_y = points.iter().map(|pos| pos.y).reduce(f32::max);
 
     let (Some(min_x), Some(max_x), Some(min_y), Some(max_y)) = (min_x, max_x, min_y, max_y) else {
         return Vec::new();
     };
 
-    let world_min = Vec2::new(min_x, min_y);
-    let world_max = Vec2::new(max_x, max_y);
-
-    let min_chunk_x = (world_min.x / chunk_world_size.x).floor() as i32;
-    let max_chunk_x = (world_max.x / chunk_world_size.x).ceil() as i32;
-    let min_chunk_y = (world_min.y / chunk_world_size.y).floor() as i32;
-    let max_chunk_y = (world_max.y / chunk_world_size.y).ceil() as i32;
-
-    if min_chunk_x > max_chunk_x || min_chunk_y > max_chunk_y {
-        return Vec::new();
-    }
+    let min_chunk_x = (min_x / chunk_world_size.x).floor() as i32;
+    let max_chunk_x = (max_x / chunk_world_size.x).floor() as i32;
+    let min_chunk_y = (min_y / chunk_world_size.y).floor() as i32;
+    let max_chunk_y = (max_y / chunk_world_size.y).floor() as i32;
 
     let count_x = (max_chunk_x - min_chunk_x + 1).max(0) as usize;
     let count_y = (max_chunk_y - min_chunk_y + 1).max(0) as usize;
@@ -567,8 +805,7 @@ fn build_chunk_mesh(
             let tile_y = tile_y_start + local_y;
             let world_x = tile_x as f32 * TILE_SIZE - half_w;
             let world_z = tile_y as f32 * TILE_SIZE - half_h;
-            let tile_index = map.tile_index(tile_x, tile_y) as usize;
-            let (uv_min, uv_max) = atlas.uv_bounds(tile_index);
+            let (uv_min, uv_max) = tile_uv_bounds(map, atlas, tile_x, tile_y);
 
             push_tile(
                 &mut positions,
@@ -592,28 +829,114 @@ fn build_chunk_mesh(
     mesh
 }
 
+// The atlas rect a tile shows, flipped like its mesh.
+fn tile_uv_bounds(
+    map: &TileMap,
+    atlas: &texture_atlas::TextureAtlas,
+    x: usize,
+    y: usize,
+) -> (Vec2, Vec2) {
+    let (flip_x, flip_y) = tile_flips(x, y);
+    atlas.uv_bounds(map.tile_index(x, y) as usize, flip_x, flip_y)
+}
+
+// Mirrors each tile by a fixed hash of its position, so few patches still look varied. Tangents
+// are generated from the flipped UVs, which keeps the mirrored normal maps lit correctly.
+fn tile_flips(x: usize, y: usize) -> (bool, bool) {
+    let hash = lattice_value(TILE_FLIP_SEED, x as i64, y as i64);
+    let quadrant = (hash * 4.0) as u32;
+    (quadrant & 1 != 0, quadrant & 2 != 0)
+}
+
 impl TileMap {
     fn tile_index(&self, x: usize, y: usize) -> u32 {
         self.tiles[y * self.width + x]
     }
-}
 
-fn random_map(width: usize, height: usize) -> TileMap {
-    let mut tiles = Vec::with_capacity(width * height);
-    let mut rng = rand::thread_rng();
-    for _y in 0..height {
-        for _x in 0..width {
-            tiles.push(rng.gen::<u32>());
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height
+    }
+
+    // Uncorrelated tiles drawn row by row from `rng`. Any `Rng` works; a seeded `StdRng` gives
+    // the same map on every platform.
+    fn with_rng(width: usize, height: usize, rng: &mut impl Rng) -> Self {
+        let mut tiles = Vec::with_capacity(width * height);
+        for _y in 0..height {
+            for _x in 0..width {
+                tiles.push(rng.gen::<u32>());
+            }
+        }
+
+        TileMap {
+            width,
+            height,
+            tiles,
         }
     }
 
-    TileMap {
-        width,
-        height,
-        tiles,
+    // Value noise with features roughly `scale` tiles across, so neighbouring tiles form
+    // regions instead of the uncorrelated look of `with_rng`. Same seed, same map.
+    #[allow(dead_code)] // Startup still uses `with_rng`; no flag selects noise maps yet.
+    fn from_noise(width: usize, height: usize, seed: u64, scale: f32) -> Self {
+        assert!(scale > 0.0, "noise scale must be positive");
+        let mut tiles = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let value = value_noise(seed, x as f32 / scale, y as f32 / scale);
+                let variant = (value * TILE_VARIANTS as f32) as u32;
+                tiles.push(variant.min(TILE_VARIANTS - 1));
+            }
+        }
+
+        TileMap {
+            width,
+            height,
+            tiles,
+        }
     }
 }
 
+// Smoothly interpolated lattice noise in [0, 1).
+fn value_noise(seed: u64, x: f32, y: f32) -> f32 {
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let tx = smoothstep(x - x0);
+    let ty = smoothstep(y - y0);
+    let (ix, iy) = (x0 as i64, y0 as i64);
+
+    let top = lerp(
+        lattice_value(seed, ix, iy),
+        lattice_value(seed, ix + 1, iy),
+        tx,
+    );
+    let bottom = lerp(
+        lattice_value(seed, ix, iy + 1),
+        lattice_value(seed, ix + 1, iy + 1),
+        tx,
+    );
+    lerp(top, bottom, ty)
+}
+
+fn lattice_value(seed: u64, x: i64, y: i64) -> f32 {
+    // SplitMix64 finalizer; integer-only so maps are identical across platforms.
+    let mut hash = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    hash ^= hash >> 31;
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[allow(clippy::too_many_arguments)] // One output buffer per vertex attribute.
 fn push_tile(
     positions: &mut Vec<[f32; 3]>,
     normals: &mut Vec<[f32; 3]>,
@@ -633,12 +956,7 @@ fn push_tile(
     let normal = [0.0, 1.0, 0.0];
 
     let base = positions.len() as u32;
-    positions.extend_from_slice(&[
-        [x0, y, z0],
-        [x1, y, z0],
-        [x1, y, z1],
-        [x0, y, z1],
-    ]);
+    positions.extend_from_slice(&[[x0, y, z0], [x1, y, z0], [x1, y, z1], [x0, y, z1]]);
     normals.extend_from_slice(&[normal; 4]);
     uvs.extend_from_slice(&[
         [uv_min.x, uv_min.y],
@@ -653,24 +971,25 @@ fn push_tile(
 fn update_astronaut_movement(
     time: Res<Time>,
     mouse_buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    minimap_layout: Res<minimap::MinimapLayout>,
     windows: Query<&Window, With<PrimaryWindow>>,
     camera_query: Query<(&Camera, &GlobalTransform), With<isometric::IsoCameraTag>>,
     mut astronauts: Query<(&mut Transform, &mut AstronautController), With<Astronaut>>,
 ) {
-    if mouse_buttons.pressed(MouseButton::Left) {
+    if mouse_buttons.pressed(MouseButton::Left) && !keys.pressed(tile_editor::PAINT_KEY) {
         let window = windows.get_single().ok();
-        let cursor_pos = window.and_then(|window| window.cursor_position());
+        let cursor_pos = window
+            .and_then(|window| window.cursor_position())
+            .filter(|&cursor_pos| !minimap_layout.contains(cursor_pos));
         let camera = camera_query.get_single().ok();
         if let (Some(cursor_pos), Some((camera, camera_transform))) = (cursor_pos, camera) {
             if let Some(world_pos) =
                 isometric::cursor_world_on_plane(camera, camera_transform, cursor_pos)
             {
                 for (transform, mut controller) in &mut astronauts {
-                    controller.target = Vec3::new(
-                        world_pos.x,
-                        transform.translation.y,
-                        world_pos.z,
-                    );
+                    controller.target =
+                        Vec3::new(world_pos.x, transform.translation.y, world_pos.z);
                 }
             }
         }
@@ -687,8 +1006,7 @@ fn update_astronaut_movement(
         }
 
         let dir = to_target / distance;
-        let target_rot =
-            Quat::from_rotation_y(dir.x.atan2(dir.z) + ASTRONAUT_FORWARD_YAW_OFFSET);
+        let target_rot = Quat::from_rotation_y(dir.x.atan2(dir.z) + ASTRONAUT_FORWARD_YAW_OFFSET);
         let turn_t = (controller.turn_speed * dt).clamp(0.0, 1.0);
         transform.rotation = transform.rotation.slerp(target_rot, turn_t);
 
@@ -728,3 +1046,195 @@ fn update_astronaut_animation_state(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::mock::StepRng;
+
+    #[test]
+    fn sun_light_shines_away_from_the_sun_and_fades_below_the_horizon() {
+        let (rotation, illuminance) = sun_light(Vec3::new(0.0, 1.0, 1.0));
+        let light_dir = rotation * Vec3::NEG_Z;
+        assert!((light_dir - Vec3::new(0.0, -1.0, -1.0).normalize()).length() < 1e-5);
+        assert!((illuminance - SUN_ILLUMINANCE * 0.5f32.sqrt()).abs() < 1e-2);
+
+        assert_eq!(sun_light(Vec3::new(1.0, -0.2, 0.0)).1, 0.0);
+    }
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn map_args_default_to_grid_constants() {
+        let map_args = parse_map_args(&[]).unwrap();
+        assert_eq!((map_args.width, map_args.height), (GRID_WIDTH, GRID_HEIGHT));
+        assert_eq!(map_args.seed, None);
+
+        let map_args = parse_map_args(&args(&["512", "64", "42"])).unwrap();
+        assert_eq!((map_args.width, map_args.height), (512, 64));
+        assert_eq!(map_args.seed, Some(42));
+    }
+
+    #[test]
+    fn map_args_reject_invalid_values() {
+        assert!(parse_map_args(&args(&["512"])).is_none());
+        assert!(parse_map_args(&args(&["500", "64"])).is_none());
+        assert!(parse_map_args(&args(&["0", "64"])).is_none());
+        assert!(parse_map_args(&args(&["64", "64", "seed"])).is_none());
+    }
+
+    // A camera looking straight down at `center`, one world unit per screen pixel.
+    fn top_down(center: Vec2, screen_size: Vec2) -> impl Fn(Vec2) -> Option<Ray> {
+        move |screen_pos| {
+            let offset = screen_pos - screen_size * 0.5;
+            Some(Ray {
+                origin: Vec3::new(center.x + offset.x, 10.0, center.y + offset.y),
+                direction: Vec3::NEG_Y,
+            })
+        }
+    }
+
+    fn square_map(size: usize) -> TileMap {
+        TileMap {
+            width: size,
+            height: size,
+            tiles: vec![0; size * size],
+        }
+    }
+
+    #[test]
+    fn centered_camera_sees_the_middle_chunks() {
+        let map = square_map(CHUNK_SIZE * 8);
+        let chunk_world = CHUNK_SIZE as f32 * TILE_SIZE;
+        // Just under two chunks across, centered on the map, touches the middle four.
+        let screen = Vec2::splat(chunk_world * 1.5);
+        let mut chunks = map_chunks_in_view(&map, screen, top_down(Vec2::ZERO, screen));
+        chunks.sort();
+        assert_eq!(chunks, [(3, 3), (3, 4), (4, 3), (4, 4)]);
+    }
+
+    #[test]
+    fn camera_panned_off_the_map_sees_only_edge_chunks() {
+        let map = square_map(CHUNK_SIZE * 8);
+        let chunk_world = CHUNK_SIZE as f32 * TILE_SIZE;
+        let screen = Vec2::splat(chunk_world);
+        let corner = Vec2::splat(-4.0 * chunk_world);
+        assert_eq!(
+            map_chunks_in_view(&map, screen, top_down(corner, screen)),
+            [(0, 0)]
+        );
+        let beyond = Vec2::splat(-10.0 * chunk_world);
+        assert!(map_chunks_in_view(&map, screen, top_down(beyond, screen)).is_empty());
+    }
+
+    #[test]
+    fn degenerate_screen_sees_the_chunk_under_it() {
+        let map = square_map(CHUNK_SIZE * 8);
+        let chunk_world = CHUNK_SIZE as f32 * TILE_SIZE;
+        let center = Vec2::splat(0.5 * chunk_world);
+        let chunks = map_chunks_in_view(&map, Vec2::ZERO, top_down(center, Vec2::ZERO));
+        assert_eq!(chunks, [(4, 4)]);
+        assert!(map_chunks_in_view(&map, Vec2::ONE, |_| None).is_empty());
+    }
+
+    #[test]
+    fn chunks_covering_includes_every_visible_point() {
+        let chunk_world_size = Vec2::splat(CHUNK_SIZE as f32 * TILE_SIZE);
+        // A rotated quad, like the ground footprint of the isometric viewport.
+        let footprint = [
+            Vec2::new(0.0, -90.0),
+            Vec2::new(130.0, 0.0),
+            Vec2::new(0.0, 90.0),
+            Vec2::new(-130.0, 0.0),
+        ];
+        for offset in [
+            Vec2::ZERO,
+            Vec2::new(31.9, -0.1),
+            Vec2::new(-517.3, 244.0),
+            Vec2::new(10_000.0, -10_000.0),
+        ] {
+            let corners: Vec<Vec2> = footprint.iter().map(|&corner| corner + offset).collect();
+            let chunks = chunks_covering(&corners, chunk_world_size);
+            for step_a in 0..=10 {
+                for step_b in 0..=10 {
+                    let (a, b) = (step_a as f32 / 10.0, step_b as f32 / 10.0);
+                    let top = corners[0].lerp(corners[1], a);
+                    let bottom = corners[3].lerp(corners[2], a);
+                    let point = top.lerp(bottom, b);
+                    let chunk = (
+                        (point.x / chunk_world_size.x).floor() as i32,
+                        (point.y / chunk_world_size.y).floor() as i32,
+                    );
+                    assert!(chunks.contains(&chunk), "offset={offset}, point={point}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn world_positions_map_to_tiles() {
+        let map = TileMap::from_noise(32, 16, 1, 4.0);
+        // The map is centred on the origin, so tile (0, 0) starts at (-64, -32).
+        assert_eq!(world_to_tile(&map, Vec2::new(-64.0, -32.0)), (0, 0));
+        assert_eq!(world_to_tile(&map, Vec2::new(0.5, 0.5)), (16, 8));
+        assert_eq!(world_to_tile(&map, Vec2::new(-64.1, 31.9)), (-1, 15));
+        assert!(map.contains(31, 15));
+        assert!(!map.contains(-1, 15));
+        assert!(!map.contains(32, 0));
+    }
+
+    #[test]
+    fn rng_map_is_row_major_and_deterministic_for_a_seed() {
+        let map = TileMap::with_rng(16, 2, &mut StepRng::new(0, 1));
+        assert_eq!(map.tile_index(3, 0), 3);
+        assert_eq!(map.tile_index(3, 1), 19);
+
+        let a = TileMap::with_rng(32, 32, &mut StdRng::seed_from_u64(42));
+        let b = TileMap::with_rng(32, 32, &mut StdRng::seed_from_u64(42));
+        let c = TileMap::with_rng(32, 32, &mut StdRng::seed_from_u64(43));
+        assert_eq!(a.tiles, b.tiles);
+        assert_ne!(a.tiles, c.tiles);
+    }
+
+    #[test]
+    fn noise_map_is_deterministic_for_a_seed() {
+        let a = TileMap::from_noise(32, 32, 42, 8.0);
+        let b = TileMap::from_noise(32, 32, 42, 8.0);
+        let c = TileMap::from_noise(32, 32, 43, 8.0);
+        assert_eq!(a.tiles, b.tiles);
+        assert_ne!(a.tiles, c.tiles);
+        assert!(a.tiles.iter().all(|&tile| tile < TILE_VARIANTS));
+        assert_eq!(
+            [a.tile_index(0, 0), a.tile_index(5, 3), a.tile_index(31, 31)],
+            [41, 40, 6]
+        );
+    }
+
+    #[test]
+    fn noise_map_neighbours_are_correlated() {
+        let map = TileMap::from_noise(64, 64, 7, 8.0);
+        let mut neighbour_diff = 0;
+        for y in 0..map.height {
+            for x in 1..map.width {
+                neighbour_diff += map.tile_index(x, y).abs_diff(map.tile_index(x - 1, y));
+            }
+        }
+        let mean = neighbour_diff as f32 / (map.height * (map.width - 1)) as f32;
+        // Uncorrelated tiles would differ by about TILE_VARIANTS / 3 on average.
+        assert!(mean < TILE_VARIANTS as f32 / 8.0, "mean={mean}");
+    }
+
+    #[test]
+    fn tile_flips_are_stable_and_use_every_orientation() {
+        let mut seen = HashSet::new();
+        for y in 0..16 {
+            for x in 0..16 {
+                assert_eq!(tile_flips(x, y), tile_flips(x, y));
+                seen.insert(tile_flips(x, y));
+            }
+        }
+        assert_eq!(seen.len(), 4);
+    }
+}
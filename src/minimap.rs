@@ -0,0 +1,281 @@
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::prelude::*;
+use bevy::render::camera::{ScalingMode, Viewport};
+use bevy::render::mesh::{Indices, Mesh};
+use bevy::render::render_resource::{Extent3d, PrimitiveTopology, TextureDimension, TextureFormat};
+use bevy::render::view::RenderLayers;
+use bevy::window::PrimaryWindow;
+
+use crate::isometric::{self, IsoCamera, IsoCameraTag};
+use crate::{TileMap, TILE_SIZE, TILE_VARIANTS};
+
+// The minimap lives on its own render layer so the main camera never sees it.
+const MINIMAP_LAYER: u8 = 1;
+// Longest minimap side and distance from the window's bottom-right corner, in logical pixels.
+const MINIMAP_MAX_SIZE: f32 = 192.0;
+const MINIMAP_MARGIN: f32 = 12.0;
+const OUTLINE_COLOR: Color = Color::rgb(1.0, 0.85, 0.2);
+
+// Where the minimap is drawn this frame, in logical window pixels with a top-left origin.
+#[derive(Resource, Default)]
+pub struct MinimapLayout {
+    pub rect: Option<Rect>,
+}
+
+impl MinimapLayout {
+    pub fn contains(&self, cursor_pos: Vec2) -> bool {
+        self.rect.is_some_and(|rect| rect.contains(cursor_pos))
+    }
+}
+
+#[derive(Component)]
+pub struct MinimapCamera;
+
+#[derive(Component)]
+pub struct MinimapOutline;
+
+pub fn spawn_minimap(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    map: Res<TileMap>,
+) {
+    let layer = RenderLayers::layer(MINIMAP_LAYER);
+    let size = Vec2::new(map.width as f32, map.height as f32);
+
+    let image = images.add(minimap_image(&map));
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(minimap_quad(size)),
+            material: materials.add(StandardMaterial {
+                base_color_texture: Some(image),
+                unlit: true,
+                cull_mode: None,
+                ..default()
+            }),
+            ..default()
+        },
+        layer,
+    ));
+
+    let mut outline = Mesh::new(PrimitiveTopology::LineStrip);
+    outline.insert_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0f32; 3]; 5]);
+    outline.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0f32, 1.0, 0.0]; 5]);
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(outline),
+            material: materials.add(StandardMaterial {
+                base_color: OUTLINE_COLOR,
+                unlit: true,
+                ..default()
+            }),
+            ..default()
+        },
+        MinimapOutline,
+        layer,
+    ));
+
+    // Looks straight down with -Z up, so tile rows run down the minimap like image rows.
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                order: 1,
+                is_active: false,
+                ..default()
+            },
+            camera_3d: Camera3d {
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 10.0, 0.0).looking_at(Vec3::ZERO, -Vec3::Z),
+            projection: OrthographicProjection {
+                scaling_mode: ScalingMode::Fixed {
+                    width: size.x,
+                    height: size.y,
+                },
+                near: -100.0,
+                far: 100.0,
+                ..default()
+            }
+            .into(),
+            tonemapping: Tonemapping::None,
+            ..default()
+        },
+        MinimapCamera,
+        layer,
+    ));
+}
+
+// Keeps the minimap pinned to the window corner and redraws the main view's footprint.
+pub fn update_minimap(
+    map: Res<TileMap>,
+    mut layout: ResMut<MinimapLayout>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut minimap_camera: Query<&mut Camera, (With<MinimapCamera>, Without<IsoCameraTag>)>,
+    main_camera: Query<(&Camera, &GlobalTransform), With<IsoCameraTag>>,
+    outline: Query<&Handle<Mesh>, With<MinimapOutline>>,
+) {
+    let (Ok(window), Ok(mut camera)) = (windows.get_single(), minimap_camera.get_single_mut())
+    else {
+        return;
+    };
+
+    let window_size = Vec2::new(window.width(), window.height());
+    let size = minimap_size(map.width, map.height, MINIMAP_MAX_SIZE);
+    let fits = window_size.cmpge(size + 2.0 * MINIMAP_MARGIN).all();
+    layout.rect = fits.then(|| {
+        let max = window_size - MINIMAP_MARGIN;
+        Rect::from_corners(max - size, max)
+    });
+    camera.is_active = layout.rect.is_some();
+    camera.viewport = layout.rect.map(|rect| {
+        let scale = window.scale_factor() as f32;
+        Viewport {
+            physical_position: (rect.min * scale).as_uvec2(),
+            physical_size: (rect.size() * scale).as_uvec2().max(UVec2::ONE),
+            ..default()
+        }
+    });
+
+    let (Ok((camera, camera_transform)), Ok(handle)) =
+        (main_camera.get_single(), outline.get_single())
+    else {
+        return;
+    };
+    let Some(footprint) = view_footprint(camera, camera_transform) else {
+        return;
+    };
+    if let Some(mesh) = meshes.get_mut(handle) {
+        // Minimap units are tiles, centered like the world, slightly above the map quad.
+        let positions: Vec<[f32; 3]> = footprint
+            .iter()
+            .chain(footprint.first())
+            .map(|corner| [corner.x / TILE_SIZE, 0.1, corner.y / TILE_SIZE])
+            .collect();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    }
+}
+
+pub fn minimap_click(
+    map: Res<TileMap>,
+    layout: Res<MinimapLayout>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut camera: ResMut<IsoCamera>,
+) {
+    if !mouse_buttons.pressed(MouseButton::Left) {
+        return;
+    }
+    let cursor_pos = windows
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position());
+    let (Some(cursor_pos), Some(rect)) = (cursor_pos, layout.rect) else {
+        return;
+    };
+    if let Some(world) = minimap_to_world(rect, cursor_pos, &map) {
+        camera.set_target(world);
+    }
+}
+
+// Largest size with the map's aspect ratio whose longest side is `max_size`.
+fn minimap_size(width: usize, height: usize, max_size: f32) -> Vec2 {
+    let scale = max_size / width.max(height).max(1) as f32;
+    Vec2::new(width as f32, height as f32) * scale
+}
+
+// World XZ position under a cursor inside the minimap rectangle.
+fn minimap_to_world(rect: Rect, cursor_pos: Vec2, map: &TileMap) -> Option<Vec2> {
+    if !rect.contains(cursor_pos) {
+        return None;
+    }
+    let fraction = (cursor_pos - rect.min) / rect.size();
+    let map_size = Vec2::new(map.width as f32, map.height as f32);
+    Some((fraction - 0.5) * map_size * TILE_SIZE)
+}
+
+// Ground XZ positions under the main camera's viewport corners, in drawing order.
+fn view_footprint(camera: &Camera, camera_transform: &GlobalTransform) -> Option<[Vec2; 4]> {
+    let size = camera.logical_viewport_size()?;
+    let corners = [
+        Vec2::ZERO,
+        Vec2::new(size.x, 0.0),
+        size,
+        Vec2::new(0.0, size.y),
+    ];
+    let mut footprint = [Vec2::ZERO; 4];
+    for (ground, corner) in footprint.iter_mut().zip(corners) {
+        let world = isometric::cursor_world_on_plane(camera, camera_transform, corner)?;
+        *ground = Vec2::new(world.x, world.z);
+    }
+    Some(footprint)
+}
+
+// One pixel per tile, shaded by tile variant.
+fn minimap_image(map: &TileMap) -> Image {
+    let mut data = Vec::with_capacity(map.width * map.height * 4);
+    for &tile in &map.tiles {
+        let shade = 64 + (tile % TILE_VARIANTS * 192 / TILE_VARIANTS) as u8;
+        data.extend_from_slice(&[shade, shade, shade, u8::MAX]);
+    }
+    Image::new(
+        Extent3d {
+            width: map.width as u32,
+            height: map.height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+// A flat XZ quad of `size` tiles centered on the origin, with V running along +Z.
+fn minimap_quad(size: Vec2) -> Mesh {
+    let (x, z) = (size.x * 0.5, size.y * 0.5);
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        vec![[-x, 0.0, -z], [x, 0.0, -z], [x, 0.0, z], [-x, 0.0, z]],
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0f32, 1.0, 0.0]; 4]);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_UV_0,
+        vec![[0.0f32, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+    );
+    mesh.set_indices(Some(Indices::U32(vec![0, 2, 1, 0, 3, 2])));
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimap_fits_the_longest_side() {
+        assert_eq!(minimap_size(512, 256, 192.0), Vec2::new(192.0, 96.0));
+        assert_eq!(minimap_size(64, 128, 192.0), Vec2::new(96.0, 192.0));
+    }
+
+    #[test]
+    fn minimap_click_maps_to_world_position() {
+        let map = TileMap {
+            width: 32,
+            height: 16,
+            tiles: vec![0; 32 * 16],
+        };
+        let rect = Rect::new(100.0, 50.0, 164.0, 82.0);
+        assert_eq!(
+            minimap_to_world(rect, Vec2::new(132.0, 66.0), &map),
+            Some(Vec2::ZERO)
+        );
+        assert_eq!(
+            minimap_to_world(rect, Vec2::new(100.0, 50.0), &map),
+            Some(Vec2::new(-16.0, -8.0) * TILE_SIZE)
+        );
+        assert_eq!(minimap_to_world(rect, Vec2::new(99.0, 66.0), &map), None);
+    }
+}
@@ -0,0 +1,400 @@
+use crate::builder::{container_problem, pipe_problem};
+use crate::engine::{
+    add_human_full, add_moxie, add_photosynthesis, add_scrubber, ContainerId, Engine, Fluid, Gas,
+    ReactionError, Solid, Volume,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Declarative description of an engine. The first container is the root; every other
+// container names a parent declared before it. Names become container names, so the built
+// engine can look them up with `container_by_name`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scene {
+    pub containers: Vec<SceneContainer>,
+    #[serde(default)]
+    pub pipes: Vec<ScenePipe>,
+    #[serde(default)]
+    pub reactions: Vec<SceneReaction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SceneContainer {
+    pub name: String,
+    #[serde(default)]
+    pub parent: Option<String>,
+    pub volume: i64,
+    #[serde(default)]
+    pub gas: Gas,
+    #[serde(default)]
+    pub fluid: Fluid,
+    #[serde(default)]
+    pub solid: Solid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScenePipe {
+    pub a: String,
+    pub b: String,
+    pub flow_rate: Gas,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SceneReaction {
+    Human {
+        container: String,
+        o2_per_tick: i64,
+        #[serde(default)]
+        water_per_tick: i64,
+    },
+    Photosynthesis {
+        container: String,
+        co2_per_tick: i64,
+    },
+    Moxie {
+        container: String,
+        co2_per_tick: i64,
+    },
+    Scrubber {
+        container: String,
+        co2_per_tick: i64,
+    },
+    Custom {
+        container: String,
+        #[serde(default)]
+        gas: Gas,
+        #[serde(default)]
+        fluid: Fluid,
+        #[serde(default)]
+        solid: Solid,
+    },
+}
+
+impl SceneReaction {
+    fn container(&self) -> &str {
+        match self {
+            SceneReaction::Human { container, .. }
+            | SceneReaction::Photosynthesis { container, .. }
+            | SceneReaction::Moxie { container, .. }
+            | SceneReaction::Scrubber { container, .. }
+            | SceneReaction::Custom { container, .. } => container,
+        }
+    }
+
+    // Mirrors the assertions in the reaction helpers so bad scenes fail with an error.
+    fn problem(&self) -> Option<&'static str> {
+        match *self {
+            SceneReaction::Human {
+                o2_per_tick,
+                water_per_tick,
+                ..
+            } if o2_per_tick < 0 || water_per_tick < 0 => Some("rates must be non-negative"),
+            SceneReaction::Photosynthesis { co2_per_tick, .. } if co2_per_tick < 0 => {
+                Some("rates must be non-negative")
+            }
+            SceneReaction::Moxie { co2_per_tick, .. }
+            | SceneReaction::Scrubber { co2_per_tick, .. }
+                if co2_per_tick < 0 =>
+            {
+                Some("rates must be non-negative")
+            }
+            SceneReaction::Moxie { co2_per_tick, .. } if co2_per_tick % 2 != 0 => {
+                Some("moxie co2_per_tick must be even")
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Scene {
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SceneError {
+    NoContainers,
+    DuplicateContainer(String),
+    UnknownContainer(String),
+    InvalidContainer {
+        name: String,
+        reason: &'static str,
+    },
+    InvalidPipe {
+        a: String,
+        b: String,
+        reason: &'static str,
+    },
+    InvalidReaction {
+        container: String,
+        reason: &'static str,
+    },
+    // The engine refused a custom reaction; `error` keeps the residual atom counts.
+    RejectedReaction {
+        container: String,
+        error: ReactionError,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::NoContainers => write!(f, "scene has no containers"),
+            SceneError::DuplicateContainer(name) => {
+                write!(f, "container \"{name}\" is declared more than once")
+            }
+            SceneError::UnknownContainer(name) => {
+                write!(f, "container \"{name}\" is not declared before it is used")
+            }
+            SceneError::InvalidContainer { name, reason } => {
+                write!(f, "container \"{name}\": {reason}")
+            }
+            SceneError::InvalidPipe { a, b, reason } => write!(f, "pipe \"{a}\"-\"{b}\": {reason}"),
+            SceneError::InvalidReaction { container, reason } => {
+                write!(f, "reaction in \"{container}\": {reason}")
+            }
+            SceneError::RejectedReaction { container, error } => {
+                write!(f, "reaction in \"{container}\": {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl Engine {
+    // Validates the whole scene up front, so the engine's own assertions never fire.
+    pub fn from_scene(scene: &Scene) -> Result<Engine, SceneError> {
+        let (root, children) = scene
+            .containers
+            .split_first()
+            .ok_or(SceneError::NoContainers)?;
+        validate_container(root)?;
+        if root.parent.is_some() {
+            return Err(SceneError::InvalidContainer {
+                name: root.name.clone(),
+                reason: "the first container is the root and cannot have a parent",
+            });
+        }
+
+        let mut engine = Engine::new(Volume::new(root.volume), root.gas, root.fluid, root.solid);
+        engine
+            .set_name(engine.root(), &root.name)
+            .expect("a new engine has no names yet");
+        for container in children {
+            validate_container(container)?;
+            let parent = container
+                .parent
+                .as_deref()
+                .ok_or(SceneError::InvalidContainer {
+                    name: container.name.clone(),
+                    reason: "only the first container may omit its parent",
+                })?;
+            let parent = lookup(&engine, parent)?;
+            engine
+                .add_named_container(
+                    parent,
+                    &container.name,
+                    Volume::new(container.volume),
+                    container.gas,
+                    container.fluid,
+                    container.solid,
+                )
+                .map_err(|taken| SceneError::DuplicateContainer(taken.name))?;
+        }
+
+        for pipe in &scene.pipes {
+            let (a, b) = (lookup(&engine, &pipe.a)?, lookup(&engine, &pipe.b)?);
+            if let Some(reason) = pipe_problem(a, b, pipe.flow_rate) {
+                return Err(SceneError::InvalidPipe {
+                    a: pipe.a.clone(),
+                    b: pipe.b.clone(),
+                    reason,
+                });
+            }
+            engine.add_pipe(a, b, pipe.flow_rate);
+        }
+
+        for reaction in &scene.reactions {
+            let container = lookup(&engine, reaction.container())?;
+            if let Some(reason) = reaction.problem() {
+                return Err(SceneError::InvalidReaction {
+                    container: reaction.container().to_owned(),
+                    reason,
+                });
+            }
+            match *reaction {
+                SceneReaction::Human {
+                    o2_per_tick,
+                    water_per_tick,
+                    ..
+                } => add_human_full(&mut engine, container, o2_per_tick, water_per_tick),
+                SceneReaction::Photosynthesis { co2_per_tick, .. } => {
+                    add_photosynthesis(&mut engine, container, co2_per_tick)
+                }
+                SceneReaction::Moxie { co2_per_tick, .. } => {
+                    add_moxie(&mut engine, container, co2_per_tick)
+                }
+                SceneReaction::Scrubber { co2_per_tick, .. } => {
+                    add_scrubber(&mut engine, container, co2_per_tick)
+                }
+                SceneReaction::Custom {
+                    gas, fluid, solid, ..
+                } => {
+                    engine
+                        .try_add_reaction(container, gas, fluid, solid)
+                        .map_err(|error| SceneError::RejectedReaction {
+                            container: reaction.container().to_owned(),
+                            error,
+                        })?;
+                }
+            }
+        }
+        Ok(engine)
+    }
+}
+
+fn validate_container(container: &SceneContainer) -> Result<(), SceneError> {
+    let volume = Volume::new(container.volume);
+    match container_problem(volume, container.gas, container.fluid, container.solid) {
+        Some(reason) => Err(SceneError::InvalidContainer {
+            name: container.name.clone(),
+            reason,
+        }),
+        None => Ok(()),
+    }
+}
+
+fn lookup(engine: &Engine, name: &str) -> Result<ContainerId, SceneError> {
+    engine
+        .container_by_name(name)
+        .ok_or_else(|| SceneError::UnknownContainer(name.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::add_human;
+
+    const HABITAT: &str = r#"{
+        "containers": [
+            {
+                "name": "atmosphere",
+                "volume": 1000,
+                "gas": { "o2": 0, "co2": 9000, "co": 0, "h2o": 0 }
+            },
+            {
+                "name": "habitat",
+                "parent": "atmosphere",
+                "volume": 10,
+                "gas": { "o2": 200, "co2": 800, "co": 0, "h2o": 0 },
+                "solid": { "ch2o": 50 }
+            }
+        ],
+        "pipes": [{
+            "a": "habitat",
+            "b": "atmosphere",
+            "flow_rate": { "o2": 0, "co2": 0, "co": 2, "h2o": 0 }
+        }],
+        "reactions": [
+            { "kind": "human", "container": "habitat", "o2_per_tick": 3 },
+            { "kind": "moxie", "container": "habitat", "co2_per_tick": 2 }
+        ]
+    }"#;
+
+    #[test]
+    fn scene_builds_the_same_engine_as_imperative_code() {
+        let engine = Engine::from_scene(&Scene::from_json(HABITAT).unwrap()).unwrap();
+        assert_eq!(
+            engine.container_by_name("habitat"),
+            Some(ContainerId::from_index(1))
+        );
+
+        let gas = |o2, co2| Gas {
+            o2,
+            co2,
+            co: 0,
+            h2o: 0,
+        };
+        let mut expected = Engine::new(
+            Volume::new(1000),
+            gas(0, 9000),
+            Fluid::zero(),
+            Solid::zero(),
+        );
+        let root = expected.root();
+        expected.set_name(root, "atmosphere").unwrap();
+        let habitat = expected
+            .add_named_container(
+                root,
+                "habitat",
+                Volume::new(10),
+                gas(200, 800),
+                Fluid::zero(),
+                Solid { ch2o: 50, ice: 0 },
+            )
+            .unwrap();
+        expected.add_pipe(
+            habitat,
+            root,
+            Gas {
+                o2: 0,
+                co2: 0,
+                co: 2,
+                h2o: 0,
+            },
+        );
+        add_human(&mut expected, habitat, 3);
+        add_moxie(&mut expected, habitat, 2);
+        assert_eq!(engine, expected);
+    }
+
+    #[test]
+    fn scene_reports_unknown_containers_and_unbalanced_reactions() {
+        let mut scene = Scene::from_json(HABITAT).unwrap();
+        scene.pipes[0].b = "greenhouse".to_owned();
+        assert_eq!(
+            Engine::from_scene(&scene),
+            Err(SceneError::UnknownContainer("greenhouse".to_owned()))
+        );
+
+        let mut scene = Scene::from_json(HABITAT).unwrap();
+        scene.reactions.push(SceneReaction::Custom {
+            container: "habitat".to_owned(),
+            gas: Gas {
+                o2: 1,
+                co2: 0,
+                co: 0,
+                h2o: 0,
+            },
+            fluid: Fluid::zero(),
+            solid: Solid::zero(),
+        });
+        let error = Engine::from_scene(&scene).unwrap_err();
+        assert_eq!(
+            error,
+            SceneError::RejectedReaction {
+                container: "habitat".to_owned(),
+                error: ReactionError::Unbalanced {
+                    carbon: 0,
+                    hydrogen: 0,
+                    oxygen: 2,
+                },
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "reaction in \"habitat\": reaction is not atom-balanced (C +0, H +0, O +2)"
+        );
+
+        let mut scene = Scene::from_json(HABITAT).unwrap();
+        scene.containers[1].volume = 0;
+        let error = Engine::from_scene(&scene).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "container \"habitat\": volume must be positive"
+        );
+    }
+}
@@ -1,65 +1,323 @@
-use std::f32::consts::TAU;
+use std::f32::consts::{PI, TAU};
+use std::f64::consts::TAU as TAU64;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct PlanetParameters {
     pub sol_seconds: f32, // Length of a mean solar day, in seconds.
-    pub year_days: f32, // Orbital period in Earth days.
-    pub axial_tilt: f32, // Obliquity in radians.
+    pub year_days: f32,   // Orbital period in Earth days.
+    pub axial_tilt: f32,  // Obliquity in radians.
 }
 
+// The f32 methods are convenient for rendering; the `_f64` variants keep sub-second precision
+// for times far from the epoch, where f32 seconds are hundreds of seconds apart.
 impl PlanetParameters {
+    // Takes the tilt in degrees, as planets are usually described; the field stores radians.
+    pub const fn new(sol_seconds: f32, year_days: f32, axial_tilt_degrees: f32) -> Self {
+        Self {
+            sol_seconds,
+            year_days,
+            axial_tilt: deg_to_rad(axial_tilt_degrees),
+        }
+    }
+
     pub fn solar_longitude(&self, time_seconds: f32) -> f32 {
+        self.solar_longitude_f64(time_seconds as f64) as f32
+    }
+
+    pub fn solar_longitude_f64(&self, time_seconds: f64) -> f64 {
         let days_since_epoch = time_seconds / 86_400.0;
-        let mean_motion = TAU / self.year_days;
-        (days_since_epoch * mean_motion).rem_euclid(TAU)
+        let mean_motion = TAU64 / self.year_days as f64;
+        (days_since_epoch * mean_motion).rem_euclid(TAU64)
     }
 
     pub fn solar_declination(&self, time_seconds: f32) -> f32 {
-        let ls = self.solar_longitude(time_seconds);
-        (self.axial_tilt.sin() * ls.sin()).asin()
+        self.solar_declination_f64(time_seconds as f64) as f32
+    }
+
+    pub fn solar_declination_f64(&self, time_seconds: f64) -> f64 {
+        let ls = self.solar_longitude_f64(time_seconds);
+        ((self.axial_tilt as f64).sin() * ls.sin()).asin()
     }
 
     pub fn local_solar_fraction(&self, time_seconds: f32, longitude: f32) -> f32 {
-        let sols_since_epoch = time_seconds / self.sol_seconds;
+        self.local_solar_fraction_f64(time_seconds as f64, longitude as f64) as f32
+    }
+
+    pub fn local_solar_fraction_f64(&self, time_seconds: f64, longitude: f64) -> f64 {
+        let sols_since_epoch = time_seconds / self.sol_seconds as f64;
         let prime_meridian = sols_since_epoch.rem_euclid(1.0);
-        (prime_meridian + longitude / TAU).rem_euclid(1.0)
+        (prime_meridian + longitude / TAU64).rem_euclid(1.0)
+    }
+
+    // Radians from local solar noon, in [-PI, PI); negative before noon.
+    pub fn hour_angle(&self, time_seconds: f32, longitude: f32) -> f32 {
+        self.hour_angle_f64(time_seconds as f64, longitude as f64) as f32
+    }
+
+    pub fn hour_angle_f64(&self, time_seconds: f64, longitude: f64) -> f64 {
+        (self.local_solar_fraction_f64(time_seconds, longitude) - 0.5) * TAU64
     }
 
     pub fn local_mean_solar_time_hours(&self, time_seconds: f32, longitude: f32) -> f32 {
-        self.local_solar_fraction(time_seconds, longitude) * 24.0
+        self.local_mean_solar_time_hours_f64(time_seconds as f64, longitude as f64) as f32
+    }
+
+    pub fn local_mean_solar_time_hours_f64(&self, time_seconds: f64, longitude: f64) -> f64 {
+        self.local_solar_fraction_f64(time_seconds, longitude) * 24.0
+    }
+
+    // Sundial time: read from the sun's actual position rather than the mean clock, so it will
+    // drift from `local_mean_solar_time_hours` by the equation of time once the orbit is
+    // eccentric. The hour angle doesn't depend on latitude, so the sun is observed from the
+    // equator, where it is simply atan2(east, up).
+    pub fn local_apparent_solar_time_hours(&self, time_seconds: f32, longitude: f32) -> f32 {
+        let equator = Location {
+            latitude: 0.0,
+            longitude,
+        };
+        let (east, up, _) = solar_direction(self, equator, time_seconds);
+        let hour_angle = east.atan2(up);
+        (12.0 + hour_angle / TAU * 24.0).rem_euclid(24.0)
+    }
+
+    // Sun position at the local mean noon nearest each of `samples` evenly spaced times over
+    // one orbital period, so the points close into a loop. The mean-motion orbit has no
+    // equation of time, so the figure-eight currently collapses to a north-south line.
+    pub fn analemma(&self, location: Location, samples: usize) -> Vec<SolarAngles> {
+        let year_seconds = self.year_days as f64 * 86_400.0;
+        let longitude = location.longitude as f64;
+        (0..samples)
+            .map(|sample| {
+                let time = year_seconds * sample as f64 / samples as f64;
+                let fraction = self.local_solar_fraction_f64(time, longitude);
+                let noon = time + (0.5 - fraction) * self.sol_seconds as f64;
+                solar_angles(self, location, noon as f32)
+            })
+            .collect()
     }
 }
 
-pub const MARS: PlanetParameters = PlanetParameters {
-    sol_seconds: 88_775.244,
-    year_days: 686.971,
-    axial_tilt: deg_to_rad(25.19),
-};
+pub const MARS: PlanetParameters = PlanetParameters::new(88_775.24, 686.971, 25.19);
+
+pub const EARTH: PlanetParameters = PlanetParameters::new(86_400.0, 365.256, 23.44);
+
+// The Moon's solar day is its synodic month, and it circles the Sun along with Earth.
+pub const LUNA: PlanetParameters = PlanetParameters::new(2_551_442.9, 365.256, 1.54);
+
+pub const PLANET_NAMES: [&str; 3] = ["mars", "earth", "luna"];
+
+// Looks up a preset by its lowercase name in `PLANET_NAMES`.
+pub fn planet_by_name(name: &str) -> Option<PlanetParameters> {
+    match name {
+        "mars" => Some(MARS),
+        "earth" => Some(EARTH),
+        "luna" => Some(LUNA),
+        _ => None,
+    }
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct Location {
-    pub latitude: f32, // Latitude in radians.
+    pub latitude: f32,  // Latitude in radians, north-positive, in [-PI/2, PI/2].
     pub longitude: f32, // Longitude in radians (east-positive).
 }
 
+impl Location {
+    // Southern latitudes and western longitudes are negative. Longitude wraps into (-180, 180].
+    pub fn from_degrees(latitude_degrees: f32, longitude_degrees: f32) -> Self {
+        assert!(
+            (-90.0..=90.0).contains(&latitude_degrees),
+            "latitude must be between -90 and 90 degrees, got {latitude_degrees}"
+        );
+        let longitude = longitude_degrees.to_radians();
+        Self {
+            latitude: latitude_degrees.to_radians(),
+            longitude: PI - (PI - longitude).rem_euclid(TAU),
+        }
+    }
+}
+
 // time+location -> sun direction
 pub fn solar_direction(
     params: &PlanetParameters,
     location: Location,
     time_seconds: f32,
 ) -> (f32, f32, f32) {
-    let lat = location.latitude;
-    let declination = params.solar_declination(time_seconds);
-    let local_fraction = params.local_solar_fraction(time_seconds, location.longitude);
-    let local_time_angle = (local_fraction - 0.5) * TAU;
+    let (east, up, north) = solar_direction_f64(params, location, time_seconds as f64);
+    normalize((east as f32, up as f32, north as f32))
+}
+
+pub fn solar_direction_f64(
+    params: &PlanetParameters,
+    location: Location,
+    time_seconds: f64,
+) -> (f64, f64, f64) {
+    let lat = location.latitude as f64;
+    let declination = params.solar_declination_f64(time_seconds);
+    let local_time_angle = params.hour_angle_f64(time_seconds, location.longitude as f64);
 
     let east = declination.cos() * local_time_angle.sin();
     let north =
         lat.cos() * declination.sin() - lat.sin() * declination.cos() * local_time_angle.cos();
     let up = lat.sin() * declination.sin() + lat.cos() * declination.cos() * local_time_angle.cos();
 
-    let (east, north, up) = normalize((east, north, up));
-    (east, up, north)
+    let length = (east * east + north * north + up * up).sqrt();
+    (east / length, up / length, north / length)
+}
+
+// Lighting tiers by solar elevation, using Earth's conventional twilight thresholds. Mars's
+// dust keeps the sky glowing after sunset, so these are a reasonable fit there too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TwilightPhase {
+    Day,
+    Civil,
+    Nautical,
+    Astronomical,
+    Night,
+}
+
+pub const CIVIL_TWILIGHT_ELEVATION: f32 = deg_to_rad(-6.0);
+pub const NAUTICAL_TWILIGHT_ELEVATION: f32 = deg_to_rad(-12.0);
+pub const ASTRONOMICAL_TWILIGHT_ELEVATION: f32 = deg_to_rad(-18.0);
+
+// Each phase includes its lower threshold, so exactly -6 degrees is still civil twilight.
+pub fn twilight_phase(elevation: f32) -> TwilightPhase {
+    if elevation >= 0.0 {
+        TwilightPhase::Day
+    } else if elevation >= CIVIL_TWILIGHT_ELEVATION {
+        TwilightPhase::Civil
+    } else if elevation >= NAUTICAL_TWILIGHT_ELEVATION {
+        TwilightPhase::Nautical
+    } else if elevation >= ASTRONOMICAL_TWILIGHT_ELEVATION {
+        TwilightPhase::Astronomical
+    } else {
+        TwilightPhase::Night
+    }
+}
+
+// Radians above the horizon, and clockwise from north (east is PI / 2).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolarAngles {
+    pub elevation: f32,
+    pub azimuth: f32,
+}
+
+pub fn solar_angles(
+    params: &PlanetParameters,
+    location: Location,
+    time_seconds: f32,
+) -> SolarAngles {
+    let (east, up, north) = solar_direction(params, location, time_seconds);
+    SolarAngles {
+        elevation: up.clamp(-1.0, 1.0).asin(),
+        azimuth: east.atan2(north).rem_euclid(TAU),
+    }
+}
+
+// Fraction of overhead-sun irradiance reaching flat ground, ignoring the atmosphere.
+pub fn irradiance_fraction(angles: SolarAngles) -> f32 {
+    angles.elevation.sin().max(0.0)
+}
+
+// Relative path length of sunlight through the atmosphere, 1 with the sun overhead, by the
+// Kasten-Young (1989) formula. It stays finite down to the horizon, where it tops out near 38,
+// and is infinite once the sun has set.
+pub fn air_mass(elevation: f32) -> f32 {
+    if elevation < 0.0 {
+        return f32::INFINITY;
+    }
+    let zenith = (PI / 2.0 - elevation).max(0.0);
+    1.0 / (zenith.cos() + 0.50572 * (96.07995 - zenith.to_degrees()).powf(-1.6364))
+}
+
+// Beer-Lambert attenuation of `base`, the irradiance above the atmosphere, for a surface facing
+// the sun. `optical_depth` is the vertical one; Mars's is about 0.5 on a clear day and several
+// in a dust storm. Zero once the sun has set, even with no atmosphere at all.
+pub fn attenuated_insolation(base: f32, elevation: f32, optical_depth: f32) -> f32 {
+    if elevation < 0.0 {
+        return 0.0;
+    }
+    base * (-optical_depth * air_mass(elevation)).exp()
+}
+
+// Samples `solar_direction` once per `refresh_seconds` span and slerps inside the span, so a
+// slowly advancing clock costs two sines per query instead of the full solar model.
+#[derive(Debug, Clone)]
+pub struct SunCache {
+    params: PlanetParameters,
+    location: Location,
+    refresh_seconds: f32,
+    span: Option<SunSpan>,
+}
+
+// Exact directions at `start` and `start + refresh_seconds`, with the angle between them.
+#[derive(Debug, Clone, Copy)]
+struct SunSpan {
+    start: f32,
+    from: (f32, f32, f32),
+    to: (f32, f32, f32),
+    angle: f32,
+}
+
+impl SunCache {
+    pub fn new(params: PlanetParameters, location: Location, refresh_seconds: f32) -> Self {
+        assert!(refresh_seconds > 0.0, "refresh interval must be positive");
+        Self {
+            params,
+            location,
+            refresh_seconds,
+            span: None,
+        }
+    }
+
+    pub fn direction(&mut self, time_seconds: f32) -> (f32, f32, f32) {
+        let span = match self.span {
+            Some(span) if self.span_contains(span.start, time_seconds) => span,
+            // Stepping into the next span reuses the sample already taken at its start.
+            Some(span) if self.span_contains(span.start + self.refresh_seconds, time_seconds) => {
+                self.sample(span.start + self.refresh_seconds, Some(span.to))
+            }
+            _ => self.sample(time_seconds, None),
+        };
+        self.span = Some(span);
+        span.at((time_seconds - span.start) / self.refresh_seconds)
+    }
+
+    fn span_contains(&self, start: f32, time_seconds: f32) -> bool {
+        (start..=start + self.refresh_seconds).contains(&time_seconds)
+    }
+
+    fn sample(&self, start: f32, from: Option<(f32, f32, f32)>) -> SunSpan {
+        let from = from.unwrap_or_else(|| solar_direction(&self.params, self.location, start));
+        let to = solar_direction(&self.params, self.location, start + self.refresh_seconds);
+        let cos_angle = from.0 * to.0 + from.1 * to.1 + from.2 * to.2;
+        SunSpan {
+            start,
+            from,
+            to,
+            angle: cos_angle.clamp(-1.0, 1.0).acos(),
+        }
+    }
+}
+
+impl SunSpan {
+    fn at(&self, t: f32) -> (f32, f32, f32) {
+        // Nearly parallel samples make slerp's weights 0/0; a normalized lerp is exact enough.
+        let (a, b) = if self.angle < 1e-4 {
+            (1.0 - t, t)
+        } else {
+            let sin_angle = self.angle.sin();
+            (
+                ((1.0 - t) * self.angle).sin() / sin_angle,
+                (t * self.angle).sin() / sin_angle,
+            )
+        };
+        normalize((
+            a * self.from.0 + b * self.to.0,
+            a * self.from.1 + b * self.to.1,
+            a * self.from.2 + b * self.to.2,
+        ))
+    }
 }
 
 fn normalize((x, y, z): (f32, f32, f32)) -> (f32, f32, f32) {
@@ -70,10 +328,15 @@ fn normalize((x, y, z): (f32, f32, f32)) -> (f32, f32, f32) {
     (x / len, y / len, z / len)
 }
 
-const fn deg_to_rad(value: f32) -> f32 {
+// Usable in consts, unlike `f32::to_radians`.
+pub const fn deg_to_rad(value: f32) -> f32 {
     value * (TAU / 360.0)
 }
 
+pub const fn rad_to_deg(value: f32) -> f32 {
+    value * (360.0 / TAU)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -121,4 +384,261 @@ mod tests {
         let b = solar_direction(&MARS, LOCATION, next_sol);
         assert!(dot(a, b) > 0.999, "dot={}", dot(a, b));
     }
+
+    #[test]
+    fn hour_angle_is_zero_at_noon_and_pi_at_midnight() {
+        let noon_fraction = (0.5 - LOCATION.longitude / TAU).rem_euclid(1.0);
+        let noon = noon_fraction * MARS.sol_seconds;
+        let midnight = noon + 0.5 * MARS.sol_seconds;
+
+        let at_noon = MARS.hour_angle(noon, LOCATION.longitude);
+        let at_midnight = MARS.hour_angle(midnight, LOCATION.longitude);
+        assert!(at_noon.abs() < 1e-3, "at_noon={at_noon}");
+        assert!(
+            (at_midnight.abs() - std::f32::consts::PI).abs() < 1e-3,
+            "at_midnight={at_midnight}"
+        );
+    }
+
+    #[test]
+    fn local_time_advances_steadily_far_from_epoch() {
+        let base = 4_000_000_000.0;
+        let longitude = LOCATION.longitude as f64;
+        let step_hours = 60.0 / MARS.sol_seconds as f64 * 24.0;
+        let mut previous = MARS.local_mean_solar_time_hours_f64(base, longitude);
+        for minute in 1..=120 {
+            let time = base + minute as f64 * 60.0;
+            let lmst = MARS.local_mean_solar_time_hours_f64(time, longitude);
+            let delta = (lmst - previous).rem_euclid(24.0);
+            assert!(
+                (delta - step_hours).abs() < 1e-6,
+                "minute={minute} delta={delta}"
+            );
+            previous = lmst;
+        }
+    }
+
+    #[test]
+    fn solar_direction_f64_moves_smoothly_far_from_the_epoch() {
+        // f32 seconds are 64 s apart out here, so only the f64 path can step by one second.
+        let base = 1.0e9;
+        let now = solar_direction_f64(&MARS, LOCATION, base);
+        let next = solar_direction_f64(&MARS, LOCATION, base + 1.0);
+        let dot64 = now.0 * next.0 + now.1 * next.1 + now.2 * next.2;
+        let expected = (TAU64 / MARS.sol_seconds as f64).cos();
+        assert!(dot64 < 1.0 && dot64 > expected - 1e-9, "dot={dot64}");
+
+        let narrowed = solar_direction(&MARS, LOCATION, 1000.0);
+        let wide = solar_direction_f64(&MARS, LOCATION, 1000.0);
+        let wide = (wide.0 as f32, wide.1 as f32, wide.2 as f32);
+        let agreement = dot(narrowed, wide);
+        assert!(agreement > 0.999_999, "dot={agreement}");
+    }
+
+    #[test]
+    fn solar_direction_is_continuous_across_the_epoch() {
+        let before = solar_direction(&MARS, LOCATION, -30.0);
+        let after = solar_direction(&MARS, LOCATION, 30.0);
+        assert!(dot(before, after) > 0.999_99, "dot={}", dot(before, after));
+
+        let sol_before = solar_direction(&MARS, LOCATION, -MARS.sol_seconds);
+        let at_epoch = solar_direction(&MARS, LOCATION, 0.0);
+        assert!(
+            dot(sol_before, at_epoch) > 0.999,
+            "dot={}",
+            dot(sol_before, at_epoch)
+        );
+
+        // Local time keeps ticking forward through zero instead of jumping at the epoch.
+        let longitude = LOCATION.longitude as f64;
+        let step_hours = 60.0 / MARS.sol_seconds as f64 * 24.0;
+        let mut previous = MARS.local_mean_solar_time_hours_f64(-3600.0, longitude);
+        for minute in -59..=60 {
+            let lmst = MARS.local_mean_solar_time_hours_f64(minute as f64 * 60.0, longitude);
+            let delta = (lmst - previous).rem_euclid(24.0);
+            assert!(
+                (delta - step_hours).abs() < 1e-9,
+                "minute={minute} delta={delta}"
+            );
+            previous = lmst;
+        }
+    }
+
+    #[test]
+    fn sun_cache_stays_close_to_the_exact_direction() {
+        let mut cache = SunCache::new(MARS, LOCATION, 600.0);
+        let base = 1_704_067_200.0;
+        // Forward through several spans, then a jump back that forces a fresh sample.
+        let times = (0..40)
+            .map(|step| base + step as f32 * 45.0)
+            .chain([base - 10_000.0]);
+        for time in times {
+            let cached = cache.direction(time);
+            let exact = solar_direction(&MARS, LOCATION, time);
+            assert!(
+                dot(cached, exact) > 0.999_99,
+                "time={time} dot={}",
+                dot(cached, exact)
+            );
+        }
+        assert_eq!(
+            cache.direction(base),
+            solar_direction(&MARS, LOCATION, base)
+        );
+    }
+
+    #[test]
+    fn apparent_time_matches_mean_time_without_eccentricity() {
+        for hour in 0..24 {
+            let time = 1_704_067_200.0 + hour as f32 * MARS.sol_seconds / 24.0;
+            let mean = MARS.local_mean_solar_time_hours(time, LOCATION.longitude);
+            let apparent = MARS.local_apparent_solar_time_hours(time, LOCATION.longitude);
+            // Compare on the clock face, so 23.999 h and 0.001 h count as close.
+            let difference = (apparent - mean + 12.0).rem_euclid(24.0) - 12.0;
+            assert!(difference.abs() < 0.01, "mean={mean} apparent={apparent}");
+        }
+    }
+
+    #[test]
+    fn new_converts_the_axial_tilt_from_degrees() {
+        let planet = PlanetParameters::new(88_775.24, 686.971, 25.19);
+        assert!((planet.axial_tilt - 0.43965).abs() < 1e-5);
+        assert!((MARS.axial_tilt - 0.43965).abs() < 1e-5);
+        assert!((rad_to_deg(planet.axial_tilt) - 25.19).abs() < 1e-4);
+    }
+
+    #[test]
+    fn from_degrees_wraps_longitude_and_keeps_latitude_sign() {
+        let gale = Location::from_degrees(-5.4, 137.8);
+        assert!((gale.latitude - deg_to_rad(-5.4)).abs() < 1e-6);
+        assert!((gale.longitude - deg_to_rad(137.8)).abs() < 1e-6);
+        let wrapped = Location::from_degrees(0.0, 190.0);
+        assert!((wrapped.longitude - deg_to_rad(-170.0)).abs() < 1e-5);
+        assert!((Location::from_degrees(0.0, -180.0).longitude - PI).abs() < 1e-6);
+    }
+
+    #[test]
+    #[should_panic(expected = "latitude must be between")]
+    fn from_degrees_rejects_latitudes_past_the_poles() {
+        Location::from_degrees(91.0, 0.0);
+    }
+
+    #[test]
+    fn southern_seasons_mirror_northern_ones() {
+        let noon_elevation = |location: Location, time: f64| {
+            let fraction = MARS.local_solar_fraction_f64(time, location.longitude as f64);
+            let noon = time + (0.5 - fraction) * MARS.sol_seconds as f64;
+            solar_angles(&MARS, location, noon as f32).elevation
+        };
+        let north = Location::from_degrees(40.0, 0.0);
+        let south = Location::from_degrees(-40.0, 0.0);
+        let half_year = MARS.year_days as f64 * 86_400.0 / 2.0;
+        let solstice = half_year / 2.0;
+        assert!(MARS.solar_declination_f64(solstice).abs() > 0.3);
+
+        // The north's summer noon sun is the south's, half a year later, and vice versa.
+        let north_summer = noon_elevation(north, solstice);
+        let south_winter = noon_elevation(south, solstice);
+        assert!(north_summer - south_winter > 0.5);
+        assert!((noon_elevation(south, solstice + half_year) - north_summer).abs() < 1e-2);
+        assert!((noon_elevation(north, solstice + half_year) - south_winter).abs() < 1e-2);
+    }
+
+    #[test]
+    fn every_planet_name_resolves_to_a_preset() {
+        for name in PLANET_NAMES {
+            assert!(planet_by_name(name).is_some(), "{name}");
+        }
+        assert_eq!(
+            planet_by_name("earth").map(|planet| planet.sol_seconds),
+            Some(86_400.0)
+        );
+        assert!(planet_by_name("Mars").is_none());
+    }
+
+    #[test]
+    fn twilight_phases_switch_at_threshold_angles() {
+        let just_below = |angle: f32| angle - 1e-4;
+        assert_eq!(twilight_phase(deg_to_rad(30.0)), TwilightPhase::Day);
+        assert_eq!(twilight_phase(0.0), TwilightPhase::Day);
+        assert_eq!(twilight_phase(just_below(0.0)), TwilightPhase::Civil);
+        assert_eq!(
+            twilight_phase(CIVIL_TWILIGHT_ELEVATION),
+            TwilightPhase::Civil
+        );
+        assert_eq!(
+            twilight_phase(just_below(CIVIL_TWILIGHT_ELEVATION)),
+            TwilightPhase::Nautical
+        );
+        assert_eq!(
+            twilight_phase(NAUTICAL_TWILIGHT_ELEVATION),
+            TwilightPhase::Nautical
+        );
+        assert_eq!(
+            twilight_phase(just_below(NAUTICAL_TWILIGHT_ELEVATION)),
+            TwilightPhase::Astronomical
+        );
+        assert_eq!(
+            twilight_phase(ASTRONOMICAL_TWILIGHT_ELEVATION),
+            TwilightPhase::Astronomical
+        );
+        assert_eq!(
+            twilight_phase(just_below(ASTRONOMICAL_TWILIGHT_ELEVATION)),
+            TwilightPhase::Night
+        );
+    }
+
+    #[test]
+    fn irradiance_fraction_is_zero_below_the_horizon_and_one_at_the_zenith() {
+        let at = |elevation| {
+            irradiance_fraction(SolarAngles {
+                elevation,
+                azimuth: 1.0,
+            })
+        };
+        assert_eq!(at(-0.2), 0.0);
+        assert_eq!(at(0.0), 0.0);
+        assert!((at(PI / 6.0) - 0.5).abs() < 1e-6);
+        assert!((at(PI / 2.0) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn air_mass_is_one_overhead_and_grows_sharply_near_the_horizon() {
+        assert!((air_mass(PI / 2.0) - 1.0).abs() < 1e-3);
+        assert!((air_mass(deg_to_rad(30.0)) - 2.0).abs() < 0.01);
+        let low = air_mass(deg_to_rad(5.0));
+        assert!(low > 10.0 && low < air_mass(0.0));
+        assert!((air_mass(0.0) - 37.9).abs() < 0.1);
+        assert_eq!(air_mass(-0.01), f32::INFINITY);
+
+        let clear = attenuated_insolation(590.0, PI / 2.0, 0.5);
+        assert!((clear - 590.0 * (-0.5f32).exp()).abs() < 0.5);
+        assert!(attenuated_insolation(590.0, deg_to_rad(10.0), 0.5) < clear / 5.0);
+        assert_eq!(attenuated_insolation(590.0, -0.1, 0.5), 0.0);
+        // Airless bodies like the Moon get the full beam by day and nothing at night.
+        assert_eq!(attenuated_insolation(1361.0, 0.1, 0.0), 1361.0);
+        assert_eq!(attenuated_insolation(1361.0, -0.1, 0.0), 0.0);
+    }
+
+    #[test]
+    fn analemma_spans_the_declination_range() {
+        let points = MARS.analemma(LOCATION, 360);
+        assert_eq!(points.len(), 360);
+        let elevations = points.iter().map(|angles| angles.elevation);
+        let highest = elevations.clone().fold(f32::MIN, f32::max);
+        let lowest = elevations.fold(f32::MAX, f32::min);
+        // Noon elevation is 90 degrees minus the latitude, plus or minus the axial tilt.
+        let equinox_noon = deg_to_rad(90.0) - LOCATION.latitude;
+        assert!(
+            (highest - (equinox_noon + MARS.axial_tilt)).abs() < 1e-2,
+            "highest={highest}"
+        );
+        assert!(
+            (lowest - (equinox_noon - MARS.axial_tilt)).abs() < 1e-2,
+            "lowest={lowest}"
+        );
+
+        let (first, last) = (points[0], points[points.len() - 1]);
+        assert!((first.elevation - last.elevation).abs() < 1e-2);
+    }
 }
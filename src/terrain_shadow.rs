@@ -0,0 +1,369 @@
+use std::sync::Arc;
+
+use bevy::pbr::{MaterialPipeline, MaterialPipelineKey};
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::render::mesh::MeshVertexBufferLayout;
+use bevy::render::render_resource::{
+    AsBindGroup, Extent3d, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+    TextureDimension, TextureFormat,
+};
+use bevy::render::texture::ImageSampler;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use futures_lite::future;
+
+use crate::heightmap_normal::Heightfield;
+use crate::{tile_uv_bounds, TerrainAssets, TileMap, TILE_SIZE};
+
+// Mask texels along each tile edge. Tiles show whole heightmap patches, so this is coarse: the
+// mask catches ridges a few texels wide, not the fine bumps the normal map shades.
+pub const SHADOW_TEXELS_PER_TILE: usize = 4;
+// Steps marched toward the sun from each texel. Few steps keep a rebuild cheap, at the cost of
+// missing ridges narrower than a step when the sun is low.
+const SHADOW_SAMPLES: usize = 24;
+// The sun moves about a quarter of a degree per minute, so the mask is rebuilt every few.
+const SHADOW_REFRESH_ANGLE: f32 = 0.0175;
+
+// The terrain's PBR inputs plus the horizon shadow mask, which the shader samples at each
+// fragment's world position rather than through the tile UVs. See assets/shaders/terrain.wgsl.
+#[derive(AsBindGroup, TypeUuid, TypePath, Debug, Clone)]
+#[uuid = "9c3f6e52-8a1d-4b7e-b0d4-5e2a7c91f0b3"]
+pub struct TerrainMaterial {
+    // World XZ of the map's minimum corner in xy, and the map's world size in zw.
+    #[uniform(0)]
+    pub map_rect: Vec4,
+    #[texture(1)]
+    #[sampler(2)]
+    pub albedo: Handle<Image>,
+    #[texture(3)]
+    #[sampler(4)]
+    pub normal_map: Handle<Image>,
+    #[texture(5)]
+    #[sampler(6)]
+    pub shadow_mask: Handle<Image>,
+}
+
+impl Material for TerrainMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/terrain.wgsl".into()
+    }
+
+    // Like the StandardMaterial the terrain used before, tiles are drawn from both sides.
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        descriptor.primitive.cull_mode = None;
+        Ok(())
+    }
+}
+
+// Horizon shadowing for the whole map, as an R8Unorm mask with `SHADOW_TEXELS_PER_TILE` texels
+// along each tile edge: 255 where the sun is visible and 0 where terrain blocks it.
+#[derive(Resource)]
+pub struct TerrainShadows {
+    // The heightmap averaged down to `SHADOW_TEXELS_PER_TILE` samples per patch edge.
+    heightmap: Arc<Heightfield>,
+    // World distance between mask texels, in the units the normal map's slopes use.
+    world_scale: f32,
+    mask: Handle<Image>,
+    sun_dir: Option<Vec3>,
+    tiles_changed: bool,
+    task: Option<Task<Image>>,
+}
+
+impl TerrainShadows {
+    // `heightmap` is the field the normal map was built from, `texel_scale` the world distance
+    // between its samples and `patch_texels` the atlas patch edge in texels. The mask starts
+    // fully lit until the first rebuild lands.
+    pub fn new(
+        heightmap: &Heightfield,
+        texel_scale: f32,
+        patch_texels: usize,
+        map: &TileMap,
+        images: &mut Assets<Image>,
+    ) -> Self {
+        let downsample = patch_texels / SHADOW_TEXELS_PER_TILE;
+        let (width, height) = (
+            map.width * SHADOW_TEXELS_PER_TILE,
+            map.height * SHADOW_TEXELS_PER_TILE,
+        );
+        let lit = vec![u8::MAX; width * height];
+        Self {
+            heightmap: Arc::new(heightmap.downsample(downsample)),
+            world_scale: texel_scale * downsample as f32,
+            mask: images.add(mask_image(width, height, lit)),
+            sun_dir: None,
+            tiles_changed: true,
+            task: None,
+        }
+    }
+
+    pub fn mask(&self) -> &Handle<Image> {
+        &self.mask
+    }
+}
+
+// Rebuilds the mask on the async compute pool whenever the sun has moved far enough to shift
+// the shadows or tiles were repainted, and swaps it in once done. One rebuild runs at a time;
+// anything that changes meanwhile is picked up by the next.
+pub fn update_terrain_shadows(
+    mut shadows: ResMut<TerrainShadows>,
+    mut images: ResMut<Assets<Image>>,
+    map: Res<TileMap>,
+    terrain: Res<TerrainAssets>,
+    lights: Query<&Transform, With<DirectionalLight>>,
+) {
+    shadows.tiles_changed |= map.is_changed();
+    if let Some(task) = &mut shadows.task {
+        let Some(mask) = future::block_on(future::poll_once(task)) else {
+            return;
+        };
+        if let Some(image) = images.get_mut(&shadows.mask) {
+            *image = mask;
+        }
+        shadows.task = None;
+    }
+
+    let Ok(light) = lights.get_single() else {
+        return;
+    };
+    // The sun light points away from the sun.
+    let sun_dir = light.back();
+    let sun_moved = shadows
+        .sun_dir
+        .is_none_or(|last| last.angle_between(sun_dir) >= SHADOW_REFRESH_ANGLE);
+    if !sun_moved && !shadows.tiles_changed {
+        return;
+    }
+
+    let tile_uvs: Vec<_> = (0..map.height)
+        .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+        .map(|(x, y)| tile_uv_bounds(&map, &terrain.atlas, x, y))
+        .collect();
+    let (heightmap, world_scale, tiles_wide) =
+        (shadows.heightmap.clone(), shadows.world_scale, map.width);
+    shadows.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+        let field = tiled_heightfield(&heightmap, &tile_uvs, tiles_wide, SHADOW_TEXELS_PER_TILE);
+        build_shadow_mask(&field, world_scale, sun_dir, SHADOW_SAMPLES)
+    }));
+    shadows.sun_dir = Some(sun_dir);
+    shadows.tiles_changed = false;
+}
+
+// World XZ of the map's minimum corner and its size, as `TerrainMaterial::map_rect` wants them.
+pub fn map_rect(map: &TileMap) -> Vec4 {
+    let size = Vec2::new(map.width as f32, map.height as f32) * TILE_SIZE;
+    Vec4::new(-size.x * 0.5, -size.y * 0.5, size.x, size.y)
+}
+
+// The heights the tiles show, `texels_per_tile` samples along each tile edge. Each tile reads
+// the heightmap rect between its (min, max) UV corners, so tiles mirrored through their UVs are
+// mirrored here too. `tile_uvs` runs row by row across `tiles_wide` tiles; samples sit at texel
+// centres.
+pub fn tiled_heightfield(
+    heightmap: &Heightfield,
+    tile_uvs: &[(Vec2, Vec2)],
+    tiles_wide: usize,
+    texels_per_tile: usize,
+) -> Heightfield {
+    let tiles_high = tile_uvs.len() / tiles_wide;
+    let (width, height) = (tiles_wide * texels_per_tile, tiles_high * texels_per_tile);
+    let (map_width, map_height) = heightmap.size();
+    let mut heights = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let (min, max) = tile_uvs[y / texels_per_tile * tiles_wide + x / texels_per_tile];
+            let texel = Vec2::new((x % texels_per_tile) as f32, (y % texels_per_tile) as f32);
+            let uv = min + (max - min) * (texel + 0.5) / texels_per_tile as f32;
+            heights.push(heightmap.sample(
+                uv.x * map_width as f32 - 0.5,
+                uv.y * map_height as f32 - 0.5,
+            ));
+        }
+    }
+    Heightfield::new(heights, width, height)
+}
+
+// `sun_dir` is (east, up, north), so +X runs along the field's rows and +Z down its columns.
+pub fn build_shadow_mask(
+    field: &Heightfield,
+    world_scale: f32,
+    sun_dir: Vec3,
+    samples: usize,
+) -> Image {
+    let (width, height) = field.size();
+    let max_height = field.heights().iter().copied().fold(f32::MIN, f32::max);
+    let mut mask_data = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let shadowed = is_shadowed(field, max_height, world_scale, x, y, sun_dir, samples);
+            mask_data.push(if shadowed { 0 } else { u8::MAX });
+        }
+    }
+
+    mask_image(width, height, mask_data)
+}
+
+fn mask_image(width: usize, height: usize, mask_data: Vec<u8>) -> Image {
+    let mut image = Image::new(
+        Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        mask_data,
+        TextureFormat::R8Unorm,
+    );
+    image.sampler_descriptor = ImageSampler::linear();
+    image
+}
+
+// Marches from the texel toward the sun until the ray clears the highest terrain or leaves
+// the field. The march always spans that whole distance, so at grazing angles the steps get
+// longer rather than the far terrain getting ignored.
+fn is_shadowed(
+    field: &Heightfield,
+    max_height: f32,
+    world_scale: f32,
+    x: usize,
+    y: usize,
+    sun_dir: Vec3,
+    samples: usize,
+) -> bool {
+    if sun_dir.y <= 0.0 {
+        return true;
+    }
+    let across = Vec2::new(sun_dir.x, sun_dir.z);
+    let across_length = across.length();
+    let start = field.sample(x as f32, y as f32);
+    if across_length <= f32::EPSILON || start >= max_height {
+        return false;
+    }
+    let step_dir = across / across_length;
+    // Height the ray gains per texel travelled.
+    let rise = sun_dir.y / across_length * world_scale;
+
+    let (width, height) = field.size();
+    let to_edge = |position: f32, direction: f32, size: usize| {
+        if direction > 0.0 {
+            ((size - 1) as f32 - position) / direction
+        } else if direction < 0.0 {
+            position / -direction
+        } else {
+            f32::INFINITY
+        }
+    };
+    let reach = ((max_height - start) / rise)
+        .min(to_edge(x as f32, step_dir.x, width))
+        .min(to_edge(y as f32, step_dir.y, height));
+    let step = reach / samples.max(1) as f32;
+    (1..=samples.max(1)).any(|index| {
+        let distance = step * index as f32;
+        let position = Vec2::new(x as f32, y as f32) + step_dir * distance;
+        field.sample(position.x, position.y) > start + rise * distance
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Flat ground with a wall `wall_height` high along column `wall_x`.
+    fn walled_field(width: usize, wall_x: usize, wall_height: f32) -> Heightfield {
+        let heights = (0..width * 4)
+            .map(|index| {
+                if index % width == wall_x {
+                    wall_height
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        Heightfield::new(heights, width, 4)
+    }
+
+    fn sun_at(elevation_degrees: f32, east: f32) -> Vec3 {
+        let elevation = elevation_degrees.to_radians();
+        Vec3::new(east * elevation.cos(), elevation.sin(), 0.0)
+    }
+
+    fn lit_row(mask: &Image, width: usize) -> Vec<bool> {
+        mask.data[width..2 * width]
+            .iter()
+            .map(|&value| value == u8::MAX)
+            .collect()
+    }
+
+    #[test]
+    fn tiles_read_their_patch_and_mirror_with_their_uvs() {
+        // Two 2x2 patches side by side, heights counting up row by row.
+        let heightmap = Heightfield::new(vec![0.0, 1.0, 10.0, 11.0, 2.0, 3.0, 12.0, 13.0], 4, 2);
+        let left = (Vec2::ZERO, Vec2::new(0.5, 1.0));
+        let right_mirrored = (Vec2::new(1.0, 0.0), Vec2::new(0.5, 1.0));
+        let field = tiled_heightfield(&heightmap, &[right_mirrored, left], 2, 2);
+        assert_eq!(field.size(), (4, 2));
+        assert_eq!(
+            field.heights(),
+            [11.0, 10.0, 0.0, 1.0, 13.0, 12.0, 2.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn map_rect_covers_the_tile_meshes() {
+        let map = TileMap {
+            width: 4,
+            height: 2,
+            tiles: vec![0; 8],
+        };
+        // Chunk meshes put tile (0, 0) at minus half the map size.
+        let rect = map_rect(&map);
+        assert_eq!(rect, Vec4::new(-2.0, -1.0, 4.0, 2.0) * TILE_SIZE);
+    }
+
+    #[test]
+    fn flat_ground_is_lit_by_day_and_dark_at_night() {
+        let field = walled_field(8, 0, 0.0);
+        let day = build_shadow_mask(&field, 1.0, sun_at(30.0, 1.0), 8);
+        assert_eq!(day.texture_descriptor.format, TextureFormat::R8Unorm);
+        assert!(day.data.iter().all(|&value| value == u8::MAX));
+        let night = build_shadow_mask(&field, 1.0, sun_at(-1.0, 1.0), 8);
+        assert!(night.data.iter().all(|&value| value == 0));
+    }
+
+    #[test]
+    fn wall_shadows_the_side_away_from_the_sun() {
+        let field = walled_field(8, 4, 2.5);
+        // At 45 degrees the wall shades the two texels behind it, but not the third.
+        let from_east = lit_row(&build_shadow_mask(&field, 1.0, sun_at(45.0, 1.0), 8), 8);
+        assert_eq!(
+            from_east,
+            [true, true, false, false, true, true, true, true]
+        );
+        let from_west = lit_row(&build_shadow_mask(&field, 1.0, sun_at(45.0, -1.0), 8), 8);
+        assert_eq!(
+            from_west,
+            [true, true, true, true, true, false, false, true]
+        );
+    }
+
+    #[test]
+    fn grazing_sun_is_blocked_by_distant_low_terrain() {
+        // At one degree the ray climbs about 1.05 units over 60 texels.
+        let ridge = |height| {
+            let heights = (0..64 * 4)
+                .map(|index| if index % 64 >= 56 { height } else { 0.0 })
+                .collect();
+            Heightfield::new(heights, 64, 4)
+        };
+        let lit_at_origin = |height| {
+            let mask = build_shadow_mask(&ridge(height), 1.0, sun_at(1.0, 1.0), 8);
+            lit_row(&mask, 64)[0]
+        };
+        assert!(lit_at_origin(0.5));
+        assert!(!lit_at_origin(2.0));
+    }
+}
@@ -1,41 +1,306 @@
 use bevy::prelude::*;
+use std::fmt;
 
 pub struct TextureAtlas {
     pub handle: Handle<Image>,
     columns: usize,
     rows: usize,
+    patch_width: usize,
+    patch_height: usize,
+    // Real patches, filled row by row; a partial last row leaves the remaining cells blank.
+    tile_count: usize,
+    // Shrinks every patch's UV rect on all sides to avoid bleeding when sampling mips.
+    inset_texels: f32,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AtlasError {
+    ZeroPatchSize,
+    WidthNotDivisible { width: usize, patch_width: usize },
+    HeightNotDivisible { height: usize, patch_height: usize },
+    Empty { width: usize, height: usize },
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtlasError::ZeroPatchSize => write!(f, "texture atlas patch size must be non-zero"),
+            AtlasError::WidthNotDivisible { width, patch_width } => write!(
+                f,
+                "texture atlas width {width} is not a multiple of the patch width {patch_width}"
+            ),
+            AtlasError::HeightNotDivisible {
+                height,
+                patch_height,
+            } => write!(
+                f,
+                "texture atlas height {height} is not a multiple of the patch height {patch_height}"
+            ),
+            AtlasError::Empty { width, height } => {
+                write!(f, "texture atlas is empty ({width}x{height} texels)")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AtlasError {}
+
 impl TextureAtlas {
-    pub fn from_image(image: &Image, patch_size: usize, handle: Handle<Image>) -> Self {
-        assert!(patch_size > 0, "texture atlas patch size must be non-zero");
+    // Patches are square unless `patch_height` is given.
+    pub fn from_image(
+        image: &Image,
+        patch_width: usize,
+        patch_height: Option<usize>,
+        handle: Handle<Image>,
+    ) -> Result<Self, AtlasError> {
+        let patch_height = patch_height.unwrap_or(patch_width);
+        if patch_width == 0 || patch_height == 0 {
+            return Err(AtlasError::ZeroPatchSize);
+        }
         let width = image.texture_descriptor.size.width as usize;
         let height = image.texture_descriptor.size.height as usize;
-        assert!(
-            width % patch_size == 0 && height % patch_size == 0,
-            "texture atlas size must be divisible by patch size"
-        );
+        if !width.is_multiple_of(patch_width) {
+            return Err(AtlasError::WidthNotDivisible { width, patch_width });
+        }
+        if !height.is_multiple_of(patch_height) {
+            return Err(AtlasError::HeightNotDivisible {
+                height,
+                patch_height,
+            });
+        }
 
-        let columns = width / patch_size;
-        let rows = height / patch_size;
-        assert!(columns > 0 && rows > 0, "texture atlas is empty");
+        let columns = width / patch_width;
+        let rows = height / patch_height;
+        if columns == 0 || rows == 0 {
+            return Err(AtlasError::Empty { width, height });
+        }
 
-        Self {
+        Ok(Self {
             handle,
             columns,
             rows,
-        }
+            patch_width,
+            patch_height,
+            tile_count: columns * rows,
+            inset_texels: 0.0,
+        })
+    }
+
+    #[allow(dead_code)] // No atlas in the game sets an inset yet.
+    pub fn with_inset_texels(mut self, inset_texels: f32) -> Self {
+        let shortest_side = self.patch_width.min(self.patch_height) as f32;
+        assert!(
+            inset_texels >= 0.0 && inset_texels * 2.0 < shortest_side,
+            "inset must be non-negative and smaller than half a patch"
+        );
+        self.inset_texels = inset_texels;
+        self
+    }
+
+    // For atlases whose last row is only partly filled, so indices never land on blank cells.
+    pub fn with_tile_count(mut self, tile_count: usize) -> Self {
+        assert!(
+            tile_count > 0 && tile_count <= self.columns * self.rows,
+            "tile count must be between 1 and the {} atlas cells",
+            self.columns * self.rows
+        );
+        self.tile_count = tile_count;
+        self
+    }
+
+    #[allow(dead_code)] // Layout getter; only the tests read it so far.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    #[allow(dead_code)] // Layout getter; only the tests read it so far.
+    pub fn rows(&self) -> usize {
+        self.rows
     }
 
-    pub fn uv_bounds(&self, index: usize) -> (Vec2, Vec2) {
-        let tile_index = index % (self.columns * self.rows);
+    // `uv_bounds` wraps indices at this count.
+    pub fn tile_count(&self) -> usize {
+        self.tile_count
+    }
+
+    // Patch width and height in texels.
+    pub fn tile_size_pixels(&self) -> (usize, usize) {
+        (self.patch_width, self.patch_height)
+    }
+
+    // Flipping swaps that axis's bounds, so a quad mapped min-to-max samples the patch mirrored.
+    pub fn uv_bounds(&self, index: usize, flip_x: bool, flip_y: bool) -> (Vec2, Vec2) {
+        let tile_index = index % self.tile_count();
         let column = tile_index % self.columns;
         let row = tile_index / self.columns;
-        let u0 = column as f32 / self.columns as f32;
-        let v0 = row as f32 / self.rows as f32;
-        let u1 = (column + 1) as f32 / self.columns as f32;
-        let v1 = (row + 1) as f32 / self.rows as f32;
+        let inset_u = self.inset_texels / (self.columns * self.patch_width) as f32;
+        let inset_v = self.inset_texels / (self.rows * self.patch_height) as f32;
+        let u0 = column as f32 / self.columns as f32 + inset_u;
+        let v0 = row as f32 / self.rows as f32 + inset_v;
+        let u1 = (column + 1) as f32 / self.columns as f32 - inset_u;
+        let v1 = (row + 1) as f32 / self.rows as f32 - inset_v;
 
+        let (u0, u1) = if flip_x { (u1, u0) } else { (u0, u1) };
+        let (v0, v1) = if flip_y { (v1, v0) } else { (v0, v1) };
         (Vec2::new(u0, v0), Vec2::new(u1, v1))
     }
+
+    // Maps a coordinate within one patch, [0, 1] on both axes, into that patch's (inset) rect.
+    #[allow(dead_code)] // Tile meshes take whole patches from `uv_bounds`.
+    pub fn remap_uv(&self, index: usize, local_uv: Vec2) -> Vec2 {
+        let (min, max) = self.uv_bounds(index, false, false);
+        min + (max - min) * local_uv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+    fn atlas_image(width: u32, height: u32) -> Image {
+        Image::new_fill(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0, 0, 0, 255],
+            TextureFormat::Rgba8Unorm,
+        )
+    }
+
+    #[test]
+    fn inset_shrinks_patch_bounds_by_texels() {
+        let image = atlas_image(256, 128);
+        let atlas = TextureAtlas::from_image(&image, 64, None, Handle::default())
+            .unwrap()
+            .with_inset_texels(0.5);
+
+        let (min, max) = atlas.uv_bounds(5, false, false);
+        assert_eq!(min, Vec2::new(0.25 + 0.5 / 256.0, 0.5 + 0.5 / 128.0));
+        assert_eq!(max, Vec2::new(0.5 - 0.5 / 256.0, 1.0 - 0.5 / 128.0));
+    }
+
+    #[test]
+    fn from_image_reports_mis_sized_atlases() {
+        let atlas = |width, height, patch_size| {
+            let image = atlas_image(width, height);
+            TextureAtlas::from_image(&image, patch_size, None, Handle::default()).err()
+        };
+        assert_eq!(atlas(256, 128, 0), Some(AtlasError::ZeroPatchSize));
+        assert_eq!(
+            atlas(250, 128, 64),
+            Some(AtlasError::WidthNotDivisible {
+                width: 250,
+                patch_width: 64
+            })
+        );
+        assert_eq!(
+            atlas(256, 100, 64),
+            Some(AtlasError::HeightNotDivisible {
+                height: 100,
+                patch_height: 64
+            })
+        );
+        assert_eq!(atlas(256, 128, 64), None);
+
+        let mut empty = atlas_image(64, 64);
+        empty.texture_descriptor.size.width = 0;
+        assert_eq!(
+            TextureAtlas::from_image(&empty, 64, None, Handle::default()).err(),
+            Some(AtlasError::Empty {
+                width: 0,
+                height: 64
+            })
+        );
+    }
+
+    #[test]
+    fn layout_getters_describe_the_grid() {
+        let image = atlas_image(256, 128);
+        let atlas = TextureAtlas::from_image(&image, 64, None, Handle::default()).unwrap();
+        assert_eq!((atlas.columns(), atlas.rows()), (4, 2));
+        assert_eq!(
+            (atlas.tile_count(), atlas.tile_size_pixels()),
+            (8, (64, 64))
+        );
+        assert_eq!(
+            atlas.uv_bounds(atlas.tile_count(), false, false),
+            atlas.uv_bounds(0, false, false)
+        );
+    }
+
+    #[test]
+    fn rectangular_patches_use_independent_width_and_height() {
+        let image = atlas_image(256, 128);
+        let atlas = TextureAtlas::from_image(&image, 64, Some(32), Handle::default()).unwrap();
+        assert_eq!((atlas.columns(), atlas.rows()), (4, 4));
+        assert_eq!(atlas.tile_size_pixels(), (64, 32));
+        assert_eq!(
+            atlas.uv_bounds(5, false, false),
+            (Vec2::new(0.25, 0.25), Vec2::new(0.5, 0.5))
+        );
+
+        let error = TextureAtlas::from_image(&image, 64, Some(48), Handle::default()).err();
+        assert_eq!(
+            error,
+            Some(AtlasError::HeightNotDivisible {
+                height: 128,
+                patch_height: 48
+            })
+        );
+    }
+
+    #[test]
+    fn remap_uv_spans_the_inset_patch() {
+        let image = atlas_image(256, 128);
+        let atlas = TextureAtlas::from_image(&image, 64, None, Handle::default())
+            .unwrap()
+            .with_inset_texels(0.5);
+
+        let (min, max) = atlas.uv_bounds(5, false, false);
+        assert_eq!(atlas.remap_uv(5, Vec2::ZERO), min);
+        assert_eq!(atlas.remap_uv(5, Vec2::ONE), max);
+        assert_eq!(atlas.remap_uv(5, Vec2::splat(0.5)), Vec2::new(0.375, 0.75));
+    }
+
+    #[test]
+    fn flipped_bounds_swap_the_original_axes() {
+        let image = atlas_image(256, 128);
+        let atlas = TextureAtlas::from_image(&image, 64, None, Handle::default())
+            .unwrap()
+            .with_inset_texels(0.5);
+
+        let (min, max) = atlas.uv_bounds(5, false, false);
+        assert_eq!(
+            atlas.uv_bounds(5, true, false),
+            (Vec2::new(max.x, min.y), Vec2::new(min.x, max.y))
+        );
+        assert_eq!(
+            atlas.uv_bounds(5, false, true),
+            (Vec2::new(min.x, max.y), Vec2::new(max.x, min.y))
+        );
+        assert_eq!(atlas.uv_bounds(5, true, true), (max, min));
+    }
+
+    #[test]
+    fn partial_last_row_wraps_before_blank_cells() {
+        // 4 x 2 cells holding 7 tiles, like an odd variant count padded out to whole rows.
+        let image = atlas_image(256, 128);
+        let atlas = TextureAtlas::from_image(&image, 64, None, Handle::default())
+            .unwrap()
+            .with_tile_count(7);
+        assert_eq!(atlas.tile_count(), 7);
+
+        let blank = (Vec2::new(0.75, 0.5), Vec2::new(1.0, 1.0));
+        for index in 0..atlas.columns() * atlas.rows() * 2 {
+            assert_ne!(atlas.uv_bounds(index, false, false), blank, "index {index}");
+        }
+        assert_eq!(
+            atlas.uv_bounds(7, false, false),
+            atlas.uv_bounds(0, false, false)
+        );
+    }
 }
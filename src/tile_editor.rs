@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use std::collections::VecDeque;
+
+use crate::isometric::{self, IsoCameraTag};
+use crate::minimap::MinimapLayout;
+use crate::{world_to_tile, TerrainChunk, TileMap, CHUNK_SIZE};
+
+// Hold to paint with left click instead of walking; Z undoes the most recent tile edit.
+pub const PAINT_KEY: KeyCode = KeyCode::P;
+const UNDO_KEY: KeyCode = KeyCode::Z;
+const UNDO_LIMIT: usize = 256;
+const BRUSH_KEYS: [KeyCode; 10] = [
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TileEdit {
+    x: usize,
+    y: usize,
+    previous: u32,
+}
+
+#[derive(Resource, Default)]
+pub struct TileEditor {
+    brush: u32,
+    // Most recent edit last; the oldest edits are forgotten past `UNDO_LIMIT`.
+    undo: VecDeque<TileEdit>,
+}
+
+impl TileEditor {
+    // Sets the tile and records the old value, unless it is off the map or already the brush.
+    fn paint(&mut self, map: &mut TileMap, x: i32, y: i32) -> Option<(usize, usize)> {
+        if !map.contains(x, y) {
+            return None;
+        }
+        let (x, y) = (x as usize, y as usize);
+        let tile = &mut map.tiles[y * map.width + x];
+        if *tile == self.brush {
+            return None;
+        }
+        if self.undo.len() == UNDO_LIMIT {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(TileEdit {
+            x,
+            y,
+            previous: *tile,
+        });
+        *tile = self.brush;
+        Some((x, y))
+    }
+
+    fn undo(&mut self, map: &mut TileMap) -> Option<(usize, usize)> {
+        let edit = self.undo.pop_back()?;
+        map.tiles[edit.y * map.width + edit.x] = edit.previous;
+        Some((edit.x, edit.y))
+    }
+}
+
+#[allow(clippy::too_many_arguments)] // Bevy system; each argument is a system param.
+pub fn edit_tiles(
+    mut commands: Commands,
+    mut editor: ResMut<TileEditor>,
+    mut map: ResMut<TileMap>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    minimap_layout: Res<MinimapLayout>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<IsoCameraTag>>,
+    chunks: Query<(Entity, &TerrainChunk)>,
+) {
+    if let Some(brush) = BRUSH_KEYS.iter().position(|&key| keys.just_pressed(key)) {
+        editor.brush = brush as u32;
+    }
+
+    let mut changed = None;
+    if keys.just_pressed(UNDO_KEY) {
+        changed = editor.undo(&mut map);
+    } else if keys.pressed(PAINT_KEY) && mouse_buttons.pressed(MouseButton::Left) {
+        let cursor_pos = windows
+            .get_single()
+            .ok()
+            .and_then(|window| window.cursor_position())
+            .filter(|&cursor_pos| !minimap_layout.contains(cursor_pos));
+        let world = cursor_pos.zip(camera_query.get_single().ok()).and_then(
+            |(cursor_pos, (camera, camera_transform))| {
+                isometric::cursor_world_on_plane(camera, camera_transform, cursor_pos)
+            },
+        );
+        if let Some(world) = world {
+            let (x, y) = world_to_tile(&map, Vec2::new(world.x, world.z));
+            changed = editor.paint(&mut map, x, y);
+        }
+    }
+
+    // Despawning the chunk makes `spawn_tile_meshes` rebuild it from the edited map.
+    if let Some((x, y)) = changed {
+        let coord = IVec2::new((x / CHUNK_SIZE) as i32, (y / CHUNK_SIZE) as i32);
+        for (entity, chunk) in &chunks {
+            if chunk.coord == coord {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paint_and_undo_restore_the_map() {
+        let mut map = TileMap {
+            width: 4,
+            height: 4,
+            tiles: vec![1; 16],
+        };
+        let mut editor = TileEditor {
+            brush: 7,
+            ..default()
+        };
+
+        assert_eq!(editor.paint(&mut map, 2, 3), Some((2, 3)));
+        assert_eq!(map.tile_index(2, 3), 7);
+        // Repainting the same value and painting off the map are no-ops.
+        assert_eq!(editor.paint(&mut map, 2, 3), None);
+        assert_eq!(editor.paint(&mut map, -1, 0), None);
+        assert_eq!(editor.paint(&mut map, 0, 4), None);
+
+        editor.brush = 5;
+        editor.paint(&mut map, 2, 3);
+        assert_eq!(editor.undo(&mut map), Some((2, 3)));
+        assert_eq!(map.tile_index(2, 3), 7);
+        assert_eq!(editor.undo(&mut map), Some((2, 3)));
+        assert_eq!(map.tiles, vec![1; 16]);
+        assert_eq!(editor.undo(&mut map), None);
+    }
+}
@@ -23,6 +23,56 @@ impl PressureScale {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct VolumeScale {
+    liters_per_unit: f32,
+}
+
+impl VolumeScale {
+    pub fn new(liters_per_unit: f32) -> Self {
+        assert!(liters_per_unit > 0.0, "liters_per_unit must be positive");
+        Self { liters_per_unit }
+    }
+
+    pub fn to_liters(self, volume_units: i64) -> f32 {
+        volume_units as f32 * self.liters_per_unit
+    }
+
+    pub fn from_liters(self, liters: f32) -> i64 {
+        assert!(liters >= 0.0, "liters must be non-negative");
+        (liters / self.liters_per_unit).round() as i64
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TemperatureScale {
+    kelvin_per_unit: f32,
+}
+
+impl TemperatureScale {
+    pub fn new(kelvin_per_unit: f32) -> Self {
+        assert!(kelvin_per_unit > 0.0, "kelvin_per_unit must be positive");
+        Self { kelvin_per_unit }
+    }
+
+    pub fn to_kelvin(self, temperature_units: i64) -> f32 {
+        temperature_units as f32 * self.kelvin_per_unit
+    }
+
+    pub fn from_kelvin(self, kelvin: f32) -> i64 {
+        assert!(kelvin >= 0.0, "kelvin must be non-negative");
+        (kelvin / self.kelvin_per_unit).round() as i64
+    }
+}
+
+// Molar masses in grams per mole, from whole-number atomic masses (H=1, C=12, O=16). With
+// those, every atom-balanced reaction also conserves mass exactly.
+pub const MOLAR_MASS_O2: i64 = 32;
+pub const MOLAR_MASS_CO2: i64 = 44;
+pub const MOLAR_MASS_CO: i64 = 28;
+pub const MOLAR_MASS_H2O: i64 = 18;
+pub const MOLAR_MASS_CH2O: i64 = 30;
+
 // 100 Pa per unit puts 6-10 units in the 600-1000 Pa Mars range.
 pub const MARS_ATMOSPHERE_PRESSURE_SCALE: PressureScale = PressureScale {
     pascal_per_unit: 100.0,